@@ -1,11 +1,8 @@
-use crate::{error::Result, history::History, utils, OutputArgs, ExcludeArgs};
+use crate::render::{Body, ContextFile};
+use crate::content::Skip;
+use crate::{error::{Error, Result}, filter::Patterns, manifest::EntryKind, output, repository::Repository, walk::{self, WalkOptions}, ContentArgs, OutputArgs, WalkArgs, FormatArgs, JobsArgs, SplitArgs, TokenArgs};
 use clap::Args;
-use globset::{Glob, GlobSetBuilder};
-use ignore::WalkBuilder;
 use log::debug;
-use std::collections::BTreeMap;
-use std::fmt::Write;
-use std::fs;
 use std::path::{Path, PathBuf};
 
 #[derive(Args, Debug)]
@@ -13,113 +10,105 @@ use std::path::{Path, PathBuf};
 pub struct CatArgs {
     /// The path to concatenate. Defaults to the current directory.
     pub path: Option<PathBuf>,
-    /// Concatenate from a snapshot ID instead of the filesystem.
-    #[arg(long, short)]
-    pub id: Option<u32>,
+    /// Concatenate from a snapshot instead of the filesystem: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`.
+    #[arg(long, short, allow_hyphen_values = true)]
+    pub id: Option<String>,
     #[command(flatten)]
-    pub exclude_args: ExcludeArgs,
+    pub walk_args: WalkArgs,
+    #[command(flatten)]
+    pub jobs_args: JobsArgs,
+    #[command(flatten)]
+    pub content_args: ContentArgs,
+    #[command(flatten)]
+    pub format_args: FormatArgs,
+    #[command(flatten)]
+    pub token_args: TokenArgs,
+    #[command(flatten)]
+    pub split_args: SplitArgs,
     #[command(flatten)]
     pub output_args: OutputArgs,
 }
 
 pub fn run(args: CatArgs) -> Result<()> {
-    let mut output = String::new();
-    let root_path = Path::new(".");
-
-    if let Some(id) = args.id {
-        cat_from_snapshot(id, root_path, &mut output)?;
+    let files = if let Some(spec) = &args.id {
+        let repo = Repository::discover(Path::new("."))?;
+        let config = crate::config_for(repo.root())?;
+        let patterns = args.walk_args.options(&config).patterns;
+        let max_file_size = args.content_args.max_file_size(&config);
+        cat_from_snapshot(repo.resolve(spec)?, &repo, &patterns, args.content_args.lossy, max_file_size)?
     } else {
         let path = args.path.unwrap_or_else(|| PathBuf::from("."));
-        cat_from_workdir(&path, &args.exclude_args.exclude, &mut output)?;
-    }
+        let config = crate::config_for(&path)?;
+        let options = args.walk_args.options(&config);
+        let max_file_size = args.content_args.max_file_size(&config);
+        cat_from_workdir(&path, &options, args.jobs_args.jobs, args.content_args.lossy, max_file_size)?
+    };
 
-    utils::handle_output(output, &args.output_args, "File content")
+    output::assemble(files, &args.format_args, &args.token_args, &args.split_args, &args.output_args, "File content")
 }
 
-fn cat_from_snapshot(id: u32, root_path: &Path, output: &mut String) -> Result<()> {
+/// Reads the files recorded in snapshot `id` that `patterns` select, listing those over
+/// `max_file_size` as skipped.
+fn cat_from_snapshot(
+    id: u32,
+    repo: &Repository,
+    patterns: &Patterns,
+    lossy: bool,
+    max_file_size: Option<u64>,
+) -> Result<Vec<ContextFile>> {
     debug!("Concatenating files from snapshot ID {}", id);
-    let history = History::load(root_path)?;
-    let snapshot = history.get_snapshot(id)?;
-    
-    let manifest: BTreeMap<PathBuf, String> = utils::get_manifest_from_hash(root_path, &snapshot.manifest_hash)?;
+    let filter = patterns.matcher(Path::new(""))?;
+    let manifest = repo.manifest(id)?.into_iter().filter(|(path, _)| filter.allows_file(path));
 
-    for (path, hash) in manifest {
-        let objects_dir = root_path.join(".devcat").join("objects");
-        match fs::read_to_string(objects_dir.join(hash)) {
-            Ok(content) => {
-                writeln!(output, "--- START FILE: {} ---", path.display())?;
-                writeln!(output, "{}", content)?;
-                writeln!(output, "--- END FILE: {} ---\n", path.display())?;
+    let mut files = Vec::new();
+    for (path, entry) in manifest {
+        let body = match repo.read_object(&entry.hash) {
+            Ok(target) if entry.kind == EntryKind::Symlink => {
+                Body::Skipped(format!("Symlink to {}", String::from_utf8_lossy(&target)))
             }
-            Err(_) => {
-                writeln!(output, "--- START FILE: {} ---", path.display())?;
-                writeln!(output, "[Could not read object as text]")?;
-                writeln!(output, "--- END FILE: {} ---\n", path.display())?;
+            Ok(bytes) if max_file_size.is_some_and(|max| bytes.len() as u64 > max) => {
+                Body::Skipped(Skip::TooLarge.describe(Some(bytes.len() as u64)))
             }
-        }
+            Ok(bytes) => Body::decode(bytes, lossy),
+            Err(e) => Body::Skipped(Skip::Unreadable(e.to_string()).describe(None)),
+        };
+        files.push(ContextFile { path, line: None, body });
     }
-    Ok(())
+    Ok(files)
 }
 
-fn cat_from_workdir(path: &Path, excludes: &[String], output: &mut String) -> Result<()> {
-    let mut skipped_items = Vec::new();
-
-    let mut glob_builder = GlobSetBuilder::new();
-    for pattern in excludes {
-        let glob = Glob::new(&format!("**/{}", pattern))?;
-        glob_builder.add(glob);
-    }
-    let exclude_set = glob_builder.build()?;
-
-    let walker = WalkBuilder::new(path)
-        .follow_links(false)
-        .filter_entry(move |entry| {
-            if entry.path().starts_with("./.devcat") {
-                return false;
-            }
-            if exclude_set.is_match(entry.path()) {
-                debug!("Excluding path via --exclude: {}", entry.path().display());
-                return false;
-            }
-            true
-        })
-        .build();
-
-    for result in walker {
-        let entry = match result {
-            Ok(entry) => entry,
-            Err(e) => {
-                let path_str = match &e {
-                    ignore::Error::WithPath { path, .. } => path.to_string_lossy().to_string(),
-                    _ => "[Unknown Path]".to_string(),
-                };
-                skipped_items.push((path_str, e.to_string()));
-                continue;
-            }
-        };
-
-        let current_path = entry.path();
-        if current_path.is_file() {
-            if utils::check_file_signature(current_path)? {
-                debug!("Skipping devcat output file: {}", current_path.display());
-                continue;
-            }
-            if let Err(e) = utils::append_file_content(
-                current_path,
-                current_path.strip_prefix(path).unwrap_or(current_path),
-                output,
-            ) {
-                skipped_items.push((current_path.to_string_lossy().to_string(), e.to_string()));
-            }
+/// Reads the files under `path` selected by `options`. Files over `max_file_size`
+/// are listed as skipped rather than left out silently.
+pub fn cat_from_workdir(
+    path: &Path,
+    options: &WalkOptions,
+    jobs: usize,
+    lossy: bool,
+    max_file_size: Option<u64>,
+) -> Result<Vec<ContextFile>> {
+    let results = walk::walk(path, options, jobs, |current_path, metadata| {
+        if !current_path.is_file() {
+            return Ok(None);
         }
-    }
+        let relative_path = current_path.strip_prefix(path).unwrap_or(current_path);
+        if let Some(max) = max_file_size
+            && metadata.is_file()
+            && metadata.len() > max
+        {
+            return Ok(Some(ContextFile::skipped(relative_path.to_path_buf(), Skip::TooLarge, metadata.len())));
+        }
+        Ok(Some(ContextFile::read(current_path, relative_path, lossy)))
+    })?;
 
+    report_skipped_items(&results.skipped);
+    Ok(results.files.into_iter().map(|(_, file)| file).collect())
+}
+
+fn report_skipped_items(skipped_items: &[(String, Error)]) {
     if !skipped_items.is_empty() {
         eprintln!("\n⚠️ The following paths were skipped due to errors:");
         for (path, error) in skipped_items {
             eprintln!("- {}: {}", path, error);
         }
     }
-
-    Ok(())
 }
@@ -0,0 +1,115 @@
+//! Splitting assembled context into size-limited parts for `--split-tokens` / `--split-bytes`.
+
+use crate::error::Result;
+use crate::render::{Body, ContextFile, Formatter};
+use crate::tokens::TokenEstimator;
+
+/// The most each part may hold, including the framing of its files.
+#[derive(Debug, Clone, Copy)]
+pub enum Limit {
+    Tokens(usize, TokenEstimator),
+    Bytes(usize),
+}
+
+impl Limit {
+    /// Sizes `text` in the limit's unit.
+    pub fn measure(&self, text: &str) -> usize {
+        match self {
+            Limit::Tokens(_, estimator) => estimator.estimate(text),
+            Limit::Bytes(_) => text.len(),
+        }
+    }
+
+    /// The most each part may hold, in the limit's unit.
+    pub fn capacity(&self) -> usize {
+        match *self {
+            Limit::Tokens(tokens, _) => tokens,
+            Limit::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// Groups `files` into parts that each fit in `limit` once rendered by `formatter`,
+/// keeping their order. The framing of the document (see [`Formatter::document`])
+/// takes `first_reserved` in the first part, which may carry extra headers, and
+/// `reserved` in every later one.
+///
+/// Files are only split when a file on its own exceeds the limit. The pieces are
+/// cut on line boundaries and carry the line they start at.
+pub fn split(
+    files: Vec<ContextFile>,
+    limit: Limit,
+    first_reserved: usize,
+    reserved: usize,
+    formatter: &dyn Formatter,
+) -> Result<Vec<Vec<ContextFile>>> {
+    let first_capacity = limit.capacity().saturating_sub(first_reserved);
+    let capacity = limit.capacity().saturating_sub(reserved).max(1);
+    let size_of = |file: &ContextFile| -> Result<usize> {
+        let mut rendered = String::new();
+        formatter.file(file, &mut rendered)?;
+        rendered.push_str(formatter.separator());
+        Ok(limit.measure(&rendered))
+    };
+
+    let mut parts: Vec<Vec<ContextFile>> = Vec::new();
+    let mut current = Vec::new();
+    let mut used = 0;
+    for file in files {
+        let size = size_of(&file)?;
+        let pieces = if size > capacity { split_file(file, limit, capacity, &size_of)? } else { vec![(file, size)] };
+        for (piece, size) in pieces {
+            let part_capacity = if parts.is_empty() { first_capacity } else { capacity };
+            // An empty first part is only closed if the piece would fit in a later one.
+            if used + size > part_capacity && (!current.is_empty() || (parts.is_empty() && size <= capacity)) {
+                parts.push(std::mem::take(&mut current));
+                used = 0;
+            }
+            used += size;
+            current.push(piece);
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    Ok(parts)
+}
+
+/// Cuts an oversized file into consecutive runs of lines that each fit in `capacity`.
+/// A single line that is too long on its own becomes a piece by itself.
+fn split_file(
+    file: ContextFile,
+    limit: Limit,
+    capacity: usize,
+    size_of: &dyn Fn(&ContextFile) -> Result<usize>,
+) -> Result<Vec<(ContextFile, usize)>> {
+    let Body::Content(content) = &file.body else {
+        let size = size_of(&file)?;
+        return Ok(vec![(file, size)]);
+    };
+    let first_line = file.line.unwrap_or(1);
+    let piece = |start: usize, text: &str| ContextFile {
+        path: file.path.clone(),
+        line: Some(first_line + start),
+        body: Body::Content(text.to_string()),
+    };
+    let framing = size_of(&piece(0, ""))?;
+
+    let mut pieces = Vec::new();
+    let (mut start, mut text, mut size) = (0, String::new(), framing);
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_size = limit.measure(line);
+        if size + line_size > capacity && !text.is_empty() {
+            pieces.push((piece(start, &text), size));
+            start = index;
+            text.clear();
+            size = framing;
+        }
+        text.push_str(line);
+        size += line_size;
+    }
+    if !text.is_empty() {
+        pieces.push((piece(start, &text), size));
+    }
+    Ok(pieces)
+}
@@ -1,6 +1,7 @@
-use crate::Result;
+use crate::{error::Error, repository::Repository, Result};
 use clap::Parser;
 use std::io::{self, Write};
+use std::path::Path;
 
 #[derive(Parser, Debug)]
 #[command(about = "Deletes the entire .devcat directory and all snapshots.")]
@@ -10,11 +11,14 @@ pub struct CleanArgs {
 }
 
 pub fn run(args: CleanArgs) -> Result<()> {
-    let history_dir = std::path::PathBuf::from(".devcat");
-    if !history_dir.exists() {
-        println!("No .devcat directory found. Nothing to clean.");
-        return Ok(());
-    }
+    let history_dir = match Repository::discover(Path::new(".")) {
+        Ok(repo) => repo.history_dir(),
+        Err(Error::NotARepository(_)) => {
+            println!("No .devcat directory found. Nothing to clean.");
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
     
     if !args.force {
         println!("This will permanently delete all devcat snapshots for this project.");
@@ -28,7 +32,7 @@ pub fn run(args: CleanArgs) -> Result<()> {
         }
     }
 
-    std::fs::remove_dir_all(history_dir)?;
-    println!("✅ .devcat directory and all snapshots have been deleted.");
+    std::fs::remove_dir_all(&history_dir)?;
+    println!("✅ {} and all snapshots have been deleted.", history_dir.display());
     Ok(())
 }
@@ -3,10 +3,25 @@ use serde::Deserialize;
 use std::fs;
 use std::path::Path;
 
-#[derive(Deserialize, Debug, Default, Clone)]
+/// Settings read from `.devcatrc` in the repository root.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct Config {
-    #[serde(default)]
     pub exclude: Vec<String>,
+    /// Only files matching one of these globs are visited. See [`crate::filter`].
+    pub include: Vec<String>,
+    /// Visit hidden files and directories.
+    pub hidden: bool,
+    /// Honour `.gitignore` and `.ignore` files. On by default.
+    pub gitignore: bool,
+    /// Leave files larger than this many bytes out of `cat` and `module` output.
+    pub max_file_size: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { exclude: Vec::new(), include: Vec::new(), hidden: false, gitignore: true, max_file_size: None }
+    }
 }
 
 pub fn load_config(root_path: &Path) -> Result<Config> {
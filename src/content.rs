@@ -0,0 +1,89 @@
+//! Deciding whether a file's bytes can go into context output as text.
+//!
+//! A file is binary if it starts with a known magic number or has a NUL byte near
+//! the start. Anything else is text; bytes that are not valid UTF-8 are either
+//! rejected or, with `--lossy`, replaced with `U+FFFD`.
+
+use crate::utils;
+use std::fmt;
+
+/// How much of a file is searched for NUL bytes.
+const SNIFF_LEN: usize = 8192;
+
+/// Signatures of common binary formats, checked against the start of the file.
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"%PDF-", "PDF document"),
+    (b"PK\x03\x04", "zip archive"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"BZh", "bzip2 archive"),
+    (b"\xfd7zXZ\x00", "xz archive"),
+    (b"\x28\xb5\x2f\xfd", "zstd archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7z archive"),
+    (b"SQLite format 3\x00", "SQLite database"),
+    (b"\x7fELF", "ELF executable"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+    (b"\xca\xfe\xba\xbe", "Mach-O or Java class file"),
+    (b"\x00asm", "WebAssembly module"),
+];
+
+/// Why a file's content was left out of the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Skip {
+    /// A known binary format, or a file containing NUL bytes.
+    Binary(&'static str),
+    /// Text that is not valid UTF-8, when lossy decoding is off.
+    NotUtf8,
+    /// Larger than `--max-file-size`.
+    TooLarge,
+    Unreadable(String),
+}
+
+impl fmt::Display for Skip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Skip::Binary(kind) => write!(f, "binary file: {}", kind),
+            Skip::NotUtf8 => write!(f, "file that is not valid UTF-8"),
+            Skip::TooLarge => write!(f, "file larger than --max-file-size"),
+            Skip::Unreadable(error) => write!(f, "unreadable file: {}", error),
+        }
+    }
+}
+
+impl Skip {
+    /// The text shown in place of the content, e.g. `Skipped binary file: PNG image (12.0 KiB)`.
+    pub fn describe(&self, size: Option<u64>) -> String {
+        let mut description = format!("Skipped {}", self);
+        if let Some(size) = size {
+            description.push_str(&format!(" ({})", utils::format_bytes(size)));
+        }
+        if *self == Skip::NotUtf8 {
+            description.push_str("; use --lossy to include it");
+        }
+        description
+    }
+}
+
+/// Returns `bytes` as text, or why it should be skipped.
+pub fn decode(bytes: Vec<u8>, lossy: bool) -> Result<String, Skip> {
+    if let Some(kind) = sniff(&bytes) {
+        return Err(Skip::Binary(kind));
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) if lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        Err(_) => Err(Skip::NotUtf8),
+    }
+}
+
+/// Names the binary format of `bytes`, if it looks like one.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, kind)) = MAGIC_NUMBERS.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(kind);
+    }
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    head.contains(&0).then_some("contains NUL bytes")
+}
@@ -1,74 +1,92 @@
-use crate::{history::History, utils, Result, OutputArgs, ExcludeArgs};
+use crate::{config, output, repository::Repository, revision, Result, OutputArgs, WalkArgs, CacheArgs, JobsArgs};
+use crate::manifest::{self, Entry, EntryKind};
 use clap::Parser;
 use similar::{ChangeTag, TextDiff};
-use std::collections::BTreeMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 #[derive(Parser, Debug)]
 #[command(about = "Diffs between snapshots or against the working directory.")]
 pub struct DiffArgs {
-    /// The first snapshot ID to compare. Defaults to the latest snapshot.
-    pub id1: Option<u32>,
-    /// The second snapshot ID to compare. If omitted, compares ID1 to the working directory.
-    pub id2: Option<u32>,
+    /// The first snapshot to compare: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`. Defaults to the current snapshot.
+    #[arg(allow_hyphen_values = true)]
+    pub id1: Option<String>,
+    /// The second snapshot to compare. If omitted, compares ID1 to the working directory.
+    #[arg(allow_hyphen_values = true)]
+    pub id2: Option<String>,
     #[command(flatten)]
     pub output_args: OutputArgs,
     #[command(flatten)]
-    pub exclude_args: ExcludeArgs,
+    pub walk_args: WalkArgs,
+    #[command(flatten)]
+    pub cache_args: CacheArgs,
+    #[command(flatten)]
+    pub jobs_args: JobsArgs,
 }
 
 pub fn run(args: DiffArgs) -> Result<()> {
-    let root_path = Path::new(".");
-    let history = History::load(root_path)?;
+    let mut repo = Repository::discover(Path::new("."))?;
+    repo.set_use_index(!args.cache_args.no_cache);
+    repo.set_jobs(args.jobs_args.jobs);
     let mut output = String::new();
 
-    let (old_manifest, new_manifest) = match (args.id1, args.id2) {
-        (Some(id1), Some(id2)) => (get_manifest(id1, &history)?, get_manifest(id2, &history)?),
-        (Some(id), None) => (get_manifest(id, &history)?, utils::get_current_manifest(root_path, &args.exclude_args.exclude)?),
-        (None, None) => {
-            let latest = history.get_latest()?;
-            (get_manifest(latest.id, &history)?, utils::get_current_manifest(root_path, &args.exclude_args.exclude)?)
-        }
+    let (from, to) = match (&args.id1, &args.id2) {
+        (Some(id1), id2) => (repo.resolve(id1)?, id2.as_deref().map(|id| repo.resolve(id)).transpose()?),
+        (None, None) => (repo.resolve(revision::CURRENT)?, None),
         _ => return Err(crate::error::Error::Format(std::fmt::Error)),
     };
 
-    let objects_dir = root_path.join(".devcat").join("objects");
-    let all_paths: BTreeMap<_, _> = old_manifest.keys().chain(new_manifest.keys()).map(|k| (k, true)).collect();
-
-    for path in all_paths.keys() {
-        let old_hash = old_manifest.get(*path);
-        let new_hash = new_manifest.get(*path);
-
-        if old_hash == new_hash { continue; }
+    let options = args.walk_args.options(&config::load_config(repo.root())?);
+    for change in repo.diff(from, to, &options)? {
+        if let (true, Some(old), Some(new)) = (change.is_mode_change(), &change.old, &change.new) {
+            output.push_str(&format!(
+                "--- a/{}\n+++ b/{}\nmode changed: {} -> {}\n",
+                change.path.display(),
+                change.path.display(),
+                describe_mode(old),
+                describe_mode(new),
+            ));
+            continue;
+        }
 
-        let old_content = match old_hash {
-            Some(hash) => fs::read_to_string(objects_dir.join(hash))?,
+        let old_content = match &change.old {
+            Some(entry) => String::from_utf8_lossy(&repo.read_object(&entry.hash)?).into_owned(),
             None => String::new(),
         };
-        
-        let new_content = match new_hash {
-            Some(_) => fs::read_to_string(root_path.join(path))?,
-            None => String::new(),
+
+        let new_content = match (&change.new, to) {
+            (Some(entry), Some(_)) => String::from_utf8_lossy(&repo.read_object(&entry.hash)?).into_owned(),
+            (Some(_), None) => {
+                let full_path = repo.root().join(&change.path);
+                let content = manifest::read_content(&full_path, &fs::symlink_metadata(&full_path)?)?;
+                String::from_utf8_lossy(&content).into_owned()
+            }
+            (None, _) => String::new(),
         };
 
-        output.push_str(&generate_diff(path, &old_content, &new_content));
+        output.push_str(&generate_diff(&change.path, &old_content, &new_content));
     }
     
     if output.is_empty() {
         println!("✅ No changes detected since last snapshot.");
     } else {
-        utils::handle_output(output, &args.output_args, "Diff")?;
+        output::handle_output(output, &args.output_args, "Diff")?;
     }
     
     Ok(())
 }
 
-fn get_manifest(id: u32, history: &History) -> Result<BTreeMap<PathBuf, String>> {
-    let snapshot = history.get_snapshot(id)?;
-    let objects_dir = Path::new(".").join(".devcat").join("objects");
-    let manifest_content = fs::read(objects_dir.join(&snapshot.manifest_hash))?;
-    serde_json::from_slice(&manifest_content).map_err(Into::into)
+/// Renders an entry's kind and permission bits, e.g. `file 644` or `symlink`.
+fn describe_mode(entry: &Entry) -> String {
+    let kind = match entry.kind {
+        EntryKind::File => "file",
+        EntryKind::Executable => "executable",
+        EntryKind::Symlink => "symlink",
+    };
+    match entry.mode {
+        Some(mode) => format!("{} {:o}", kind, mode),
+        None => kind.to_string(),
+    }
 }
 
 fn generate_diff(path: &Path, old: &str, new: &str) -> String {
@@ -20,10 +20,40 @@ pub enum Error {
     Notify(#[from] notify::Error),
     #[error("Snapshot ID `{0}` not found. Run `devcat log` to see available snapshots.")]
     SnapshotIdNotFound(u32),
+    #[error("Not a devcat repository (or any of the parent directories): {}. Run `devcat init` to create one.", .0.display())]
+    NotARepository(std::path::PathBuf),
+    #[error("This repository uses store format v{0}, but this devcat only understands up to v{1}. Upgrade devcat to use it.")]
+    UnsupportedFormat(u32, u32),
+    #[error("`.devcat/FORMAT` should contain a format version number, found `{0}`.")]
+    InvalidFormat(String),
+    #[error("Invalid metadata `{0}`. Expected `key=value`.")]
+    InvalidMetadata(String),
+    #[error("Invalid size `{0}`. Expected a number of bytes with an optional K, M or G suffix, e.g. `512K`.")]
+    InvalidSize(String),
+    #[error("No snapshot matches `{0}`. Run `devcat log` to see available snapshots.")]
+    UnknownSnapshot(String),
+    #[error("Invalid snapshot reference `{0}`: {1}.")]
+    InvalidRevision(String, String),
+    #[error("`{0}` did not match any file in the snapshot.")]
+    PathspecNotMatched(String),
+    #[error("Tag `{0}` already exists. Use --force to move it.")]
+    TagExists(String),
+    #[error("Tag `{0}` not found. Run `devcat tag` to list tags.")]
+    TagNotFound(String),
+    #[error("Invalid tag name `{0}`. Tags must start with a letter, contain only letters, digits, `.`, `_` or `-`, and not be `latest` or `current`.")]
+    InvalidTagName(String),
+    #[error("Snapshot {0} already has snapshots saved on top of it and cannot be amended. Save a new snapshot instead.")]
+    SnapshotHasChildren(u32),
     #[error("No snapshots found. Run `devcat save <message>` to create one.")]
     NoSnapshots,
     #[error("Could not find object with hash `{0}` in the object store.")]
     ObjectNotFound(String),
+    #[error("Object `{0}` is corrupt or uses an unknown encoding.")]
+    CorruptObject(String),
+    #[error("`{}` changed while the snapshot was being saved. Save again once it has been written.", .0.display())]
+    ChangedDuringSave(std::path::PathBuf),
+    #[error("Integrity check found {0} problem(s).")]
+    IntegrityCheckFailed(usize),
     #[error("Failed to format output string: {0}")]
     Format(#[from] std::fmt::Error),
 }
@@ -0,0 +1,94 @@
+//! Include and exclude glob patterns that decide which files a walk visits.
+//!
+//! Patterns match anywhere below the walk root (`*.rs` is `**/*.rs`). When both
+//! kinds are given:
+//!
+//! 1. `.devcat` and `.git` directories are always skipped, even with `--hidden`.
+//! 2. A path matching an exclude is skipped, even if it also matches an include.
+//!    Excluding a directory skips everything below it.
+//! 3. If there are includes, a file is only visited if it matches at least one.
+//!    Includes never prune directories, so `*.rs` still finds `src/main.rs`.
+//!
+//! Excludes from the command line and `.devcatrc` add up; includes given on the
+//! command line replace those from `.devcatrc`.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::utils::HISTORY_DIR;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::DirEntry;
+use log::debug;
+use std::path::{Path, PathBuf};
+
+/// The patterns in effect for a walk, as given by the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patterns {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Patterns {
+    /// Combines command-line patterns with those from `config`, following the rules above.
+    pub fn with_config(include: &[String], exclude: &[String], config: &Config) -> Self {
+        let include = if include.is_empty() { config.include.clone() } else { include.to_vec() };
+        let exclude = exclude.iter().chain(&config.exclude).cloned().collect();
+        Patterns { include, exclude }
+    }
+
+    /// Compiles the patterns for a walk of `root`; paths are matched relative to it.
+    pub fn matcher(&self, root: &Path) -> Result<PathFilter> {
+        Ok(PathFilter {
+            root: root.to_path_buf(),
+            include: if self.include.is_empty() { None } else { Some(build(&self.include)?) },
+            exclude: build(&self.exclude)?,
+        })
+    }
+}
+
+/// Compiled [`Patterns`].
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    root: PathBuf,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    /// Decides whether the walker descends into or yields `entry`; use with `filter_entry`.
+    pub fn allows_entry(&self, entry: &DirEntry) -> bool {
+        let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+        if entry.file_name() == HISTORY_DIR || (is_dir && entry.file_name() == ".git") {
+            return false;
+        }
+        // Match below the root only, so a pattern never matches one of its parent directories.
+        let path = entry.path().strip_prefix(&self.root).unwrap_or(entry.path());
+        self.allows(path, is_dir)
+    }
+
+    /// Decides whether a file at `path`, relative to the root, would be visited by a
+    /// walk: none of its parent directories may be excluded either. Used for paths that
+    /// do not come from a walk, such as snapshot manifests.
+    pub fn allows_file(&self, path: &Path) -> bool {
+        let mut parents = path.ancestors().skip(1).filter(|parent| !parent.as_os_str().is_empty());
+        parents.all(|parent| self.allows(parent, true)) && self.allows(path, false)
+    }
+
+    fn allows(&self, path: &Path, is_dir: bool) -> bool {
+        if self.exclude.is_match(path) {
+            debug!("Excluding path via --exclude: {}", path.display());
+            return false;
+        }
+        match &self.include {
+            Some(include) if !is_dir => include.is_match(path),
+            _ => true,
+        }
+    }
+}
+
+fn build(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(&format!("**/{}", pattern))?);
+    }
+    Ok(builder.build()?)
+}
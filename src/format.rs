@@ -0,0 +1,106 @@
+//! Versioning of the on-disk `.devcat` layout.
+//!
+//! The version lives in `.devcat/FORMAT` as a single number. Stores created
+//! before the file existed are treated as version 1. Readers accept every
+//! version up to [`CURRENT`]; writers upgrade older stores in place first.
+//!
+//! ```text
+//! 1   flat or fan-out objects, manifests mapping paths to bare hashes
+//! 2   fan-out objects only, manifest entries carrying kind and mode
+//! 3   snapshots record their parent; `.devcat/HEAD` names the current snapshot
+//! 4   snapshots record `metadata` (user, host, git state and `--meta` pairs)
+//! 5   snapshots record their walk settings (`walk`) for revert
+//! ```
+//!
+//! Older builds drop snapshot fields they do not know when rewriting
+//! `history.log`, so adding one needs a new version even if nothing is rewritten.
+
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::store::ObjectStore;
+use crate::utils;
+use log::debug;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// The format version this build of devcat writes.
+pub const CURRENT: u32 = 5;
+
+/// The version assumed for stores without a `FORMAT` file.
+const UNVERSIONED: u32 = 1;
+
+/// An in-place upgrade of the store in `history_dir` from version `N` to `N + 1`.
+type Upgrade = fn(history_dir: &Path) -> Result<()>;
+
+/// `UPGRADES[i]` takes a store from version `i + 1` to `i + 2`.
+const UPGRADES: [Upgrade; (CURRENT - UNVERSIONED) as usize] = [upgrade_to_fan_out, record_parents, mark_metadata, mark_walk_settings];
+
+/// Reads the format version of the store in `history_dir`. Versions below 1 are invalid.
+pub fn read(history_dir: &Path) -> Result<u32> {
+    match fs::read_to_string(history_dir.join("FORMAT")) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&version| version >= UNVERSIONED)
+            .ok_or_else(|| Error::InvalidFormat(content.trim().to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(UNVERSIONED),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn write(history_dir: &Path, version: u32) -> Result<()> {
+    utils::write_atomic(&history_dir.join("FORMAT"), format!("{}\n", version).as_bytes())
+}
+
+/// Fails if the store in `history_dir` was written by a newer devcat.
+pub fn check(history_dir: &Path) -> Result<u32> {
+    let version = read(history_dir)?;
+    if version > CURRENT {
+        return Err(Error::UnsupportedFormat(version, CURRENT));
+    }
+    Ok(version)
+}
+
+/// Brings the store in `history_dir` up to [`CURRENT`], returning the version it
+/// started at if anything was rewritten. Callers must hold the repository lock.
+pub fn upgrade(history_dir: &Path) -> Result<Option<u32>> {
+    let version = check(history_dir)?;
+    if version == CURRENT {
+        return Ok(None);
+    }
+    for (from, step) in (UNVERSIONED..).zip(UPGRADES).skip((version - UNVERSIONED) as usize) {
+        debug!("Upgrading store format from v{} to v{}", from, from + 1);
+        step(history_dir)?;
+        write(history_dir, from + 1)?;
+    }
+    Ok(Some(version))
+}
+
+/// v1 → v2: moves flat objects into fan-out directories. Bare-hash manifest
+/// entries are still read as regular files, so manifests are left as they are.
+fn upgrade_to_fan_out(history_dir: &Path) -> Result<()> {
+    ObjectStore::new(history_dir.join("objects")).migrate()?;
+    Ok(())
+}
+
+/// v2 → v3: writes out the parent links that linear histories used to imply.
+fn record_parents(history_dir: &Path) -> Result<()> {
+    match history_dir.parent() {
+        Some(root) => History::load(root)?.save(),
+        None => Ok(()),
+    }
+}
+
+/// v3 → v4: nothing to rewrite. The bump only keeps older builds, which would
+/// drop snapshot metadata, from writing to the store.
+fn mark_metadata(_history_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// v4 → v5: nothing to rewrite, as for [`mark_metadata`]; older builds would drop
+/// the recorded walk settings.
+fn mark_walk_settings(_history_dir: &Path) -> Result<()> {
+    Ok(())
+}
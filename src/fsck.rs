@@ -0,0 +1,48 @@
+use crate::{error::Error, format, integrity::ProblemKind, repository::Repository, Result};
+use clap::Parser;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[command(about = "Verifies that all snapshots and stored objects are intact.")]
+pub struct FsckArgs {}
+
+pub fn run(_args: FsckArgs) -> Result<()> {
+    let repo = Repository::discover(Path::new("."))?;
+    let report = repo.fsck()?;
+    if report.format_version < format::CURRENT {
+        println!(
+            "ℹ️ Store format v{} (current is v{}). Run `devcat migrate` to upgrade it.",
+            report.format_version,
+            format::CURRENT
+        );
+    }
+
+    for problem in &report.problems {
+        let kind = match problem.kind {
+            ProblemKind::Missing => "missing",
+            ProblemKind::Corrupt => "corrupt",
+        };
+        match &problem.path {
+            Some(path) => println!(
+                "❌ Snapshot {}: {} object {} for {}",
+                problem.snapshot_id, kind, problem.hash, path.display()
+            ),
+            None => println!("❌ Snapshot {}: {} manifest {}", problem.snapshot_id, kind, problem.hash),
+        }
+    }
+    for hash in &report.unreferenced {
+        println!("⚠️ Unreferenced object {}", hash);
+    }
+    if !report.unreferenced.is_empty() {
+        println!("Run `devcat gc` to remove unreferenced objects.");
+    }
+
+    if !report.is_ok() {
+        return Err(Error::IntegrityCheckFailed(report.problems.len()));
+    }
+    println!(
+        "✅ Checked {} objects across {} snapshots. No problems found.",
+        report.objects_checked, report.snapshots_checked
+    );
+    Ok(())
+}
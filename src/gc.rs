@@ -0,0 +1,41 @@
+use crate::{repository::Repository, utils, Result};
+use clap::Parser;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[command(about = "Deletes stored objects that no snapshot refers to, and temporary files left by interrupted saves.")]
+pub struct GcArgs {
+    /// List the objects that would be deleted without deleting them.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn run(args: GcArgs) -> Result<()> {
+    let repo = Repository::discover(Path::new("."))?;
+    let report = repo.gc(args.dry_run)?;
+
+    if report.removed.is_empty() && report.temp_files.is_empty() {
+        println!("✅ No unreferenced objects found. Nothing to collect.");
+    } else if args.dry_run {
+        for hash in &report.removed {
+            println!("Would remove {}", hash);
+        }
+        for path in &report.temp_files {
+            println!("Would remove {}", path.display());
+        }
+        println!(
+            "Would remove {} unreferenced objects and {} temporary files, reclaiming {}.",
+            report.removed.len(),
+            report.temp_files.len(),
+            utils::format_bytes(report.reclaimed_bytes)
+        );
+    } else {
+        println!(
+            "✅ Removed {} unreferenced objects and {} temporary files, reclaiming {}.",
+            report.removed.len(),
+            report.temp_files.len(),
+            utils::format_bytes(report.reclaimed_bytes)
+        );
+    }
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use crate::error::{Error, Result};
+use crate::format;
+use crate::metadata::Metadata;
 use crate::utils;
+use crate::walk::WalkOptions;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 
@@ -12,19 +15,67 @@ pub struct Snapshot {
     pub timestamp: DateTime<Utc>,
     pub message: String,
     pub manifest_hash: String,
+    /// The snapshot that was current when this one was saved. `None` for root snapshots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u32>,
+    /// Exclude patterns in effect when the snapshot was taken. `None` for snapshots
+    /// saved before they were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excludes: Option<Vec<String>>,
+    /// The rest of the walk settings in effect when the snapshot was taken.
+    #[serde(default, skip_serializing_if = "WalkRecord::is_default")]
+    pub walk: WalkRecord,
+    /// Who and what produced the snapshot; see [`crate::metadata`].
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+}
+
+/// The [`WalkOptions`] recorded with a snapshot besides its excludes, which
+/// predate format v5 and stay in [`Snapshot::excludes`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalkRecord {
+    /// Include patterns.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Whether hidden files were visited.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// Whether `.gitignore` and `.ignore` files were disregarded.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_gitignore: bool,
+}
+
+impl WalkRecord {
+    fn is_default(&self) -> bool {
+        *self == WalkRecord::default()
+    }
+}
+
+impl Snapshot {
+    /// Records which files `options` selects, so reverting later walks the same set.
+    pub fn record_walk_options(&mut self, options: &WalkOptions) {
+        self.excludes = Some(options.patterns.exclude.clone());
+        self.walk = WalkRecord {
+            include: options.patterns.include.clone(),
+            hidden: options.hidden,
+            no_gitignore: !options.gitignore,
+        };
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct History {
     pub snapshots: Vec<Snapshot>,
+    /// The snapshot the working tree was last saved as or reverted to, from `.devcat/HEAD`.
+    current: Option<u32>,
     path: PathBuf,
 }
 
 impl History {
-    pub fn load(_root_path: &Path) -> Result<Self> {
-        let history_dir = utils::ensure_history_dir_exists()?;
+    pub fn load(root_path: &Path) -> Result<Self> {
+        let history_dir = root_path.join(utils::HISTORY_DIR);
         let path = history_dir.join("history.log");
-        
+
         let mut snapshots: Vec<Snapshot> = Vec::new();
         if path.exists() {
             let file = File::open(&path)?;
@@ -37,33 +88,84 @@ impl History {
             }
         }
         snapshots.sort_by_key(|s| s.id);
-        Ok(History { snapshots, path })
+
+        // Before format v3 history was strictly linear and parents were implied.
+        if format::read(&history_dir)? < 3 {
+            let mut previous = None;
+            for snapshot in &mut snapshots {
+                snapshot.parent = snapshot.parent.or(previous);
+                previous = Some(snapshot.id);
+            }
+        }
+
+        let current = match fs::read_to_string(history_dir.join("HEAD")) {
+            Ok(content) => content.trim().parse().ok(),
+            Err(_) => None,
+        };
+        Ok(History { snapshots, current, path })
     }
 
     pub fn save(&self) -> Result<()> {
-        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&self.path)?;
+        let mut content = Vec::new();
         for snapshot in &self.snapshots {
             let line = serde_json::to_string(snapshot)?;
-            writeln!(file, "{}", line)?;
+            writeln!(content, "{}", line)?;
+        }
+        utils::write_atomic(&self.path, &content)?;
+        match self.current {
+            Some(id) => utils::write_atomic(&self.path.with_file_name("HEAD"), format!("{}\n", id).as_bytes()),
+            None => Ok(()),
         }
-        Ok(())
     }
 
-    pub fn add_snapshot(&mut self, message: String, manifest_hash: String) {
+    pub fn add_snapshot(&mut self, message: String, manifest_hash: String, options: &WalkOptions, metadata: Metadata) {
         let next_id = self.snapshots.last().map_or(1, |s| s.id + 1);
-        self.snapshots.push(Snapshot {
+        let parent = self.get_current().ok().map(|s| s.id);
+        self.current = Some(next_id);
+        let mut snapshot = Snapshot {
             id: next_id,
             timestamp: Utc::now(),
             message,
             manifest_hash,
-        });
+            parent,
+            excludes: None,
+            walk: WalkRecord::default(),
+            metadata,
+        };
+        snapshot.record_walk_options(options);
+        self.snapshots.push(snapshot);
     }
     
     pub fn get_snapshot(&self, id: u32) -> Result<&Snapshot> {
         self.snapshots.iter().find(|s| s.id == id).ok_or(Error::SnapshotIdNotFound(id))
     }
 
+    pub fn get_snapshot_mut(&mut self, id: u32) -> Result<&mut Snapshot> {
+        self.snapshots.iter_mut().find(|s| s.id == id).ok_or(Error::SnapshotIdNotFound(id))
+    }
+
     pub fn get_latest(&self) -> Result<&Snapshot> {
         self.snapshots.last().ok_or(Error::NoSnapshots)
     }
+
+    /// Returns the current snapshot, falling back to the latest one when `HEAD` is
+    /// missing or points at a snapshot that has since been pruned.
+    pub fn get_current(&self) -> Result<&Snapshot> {
+        match self.current.and_then(|id| self.get_snapshot(id).ok()) {
+            Some(snapshot) => Ok(snapshot),
+            None => self.get_latest(),
+        }
+    }
+
+    /// Makes `id` the current snapshot; new snapshots are recorded as its children.
+    pub fn set_current(&mut self, id: u32) -> Result<()> {
+        self.get_snapshot(id)?;
+        self.current = Some(id);
+        Ok(())
+    }
+
+    /// Returns the parent of `snapshot`, if it is still in the history.
+    pub fn parent_of(&self, snapshot: &Snapshot) -> Option<&Snapshot> {
+        snapshot.parent.and_then(|id| self.get_snapshot(id).ok())
+    }
 }
@@ -0,0 +1,131 @@
+use crate::error::Result;
+use crate::utils;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bump whenever `IndexEntry` changes shape or meaning; older indexes are discarded.
+const INDEX_VERSION: u32 = 1;
+
+/// Files modified this recently are not cached, since a further write within
+/// the filesystem's timestamp granularity would leave their stat info unchanged.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Stat information recorded for a file when it was last hashed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub size: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+    pub inode: u64,
+    pub hash: String,
+}
+
+impl IndexEntry {
+    fn stat(metadata: &Metadata) -> Option<(u64, u64, u32, u64)> {
+        let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some((metadata.len(), mtime.as_secs(), mtime.subsec_nanos(), inode(metadata)))
+    }
+}
+
+/// A persisted cache of content hashes keyed by path, stored in `.devcat/index`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    entries: BTreeMap<PathBuf, IndexEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Index {
+    /// Loads the index from `history_dir`, starting empty if it is missing, unreadable, or outdated.
+    pub fn load(history_dir: &Path) -> Self {
+        let path = history_dir.join("index");
+        let loaded = fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<Index>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+
+        match loaded {
+            Some(mut index) => {
+                index.path = path;
+                index
+            }
+            None => {
+                debug!("Starting with an empty index at {}", path.display());
+                Index { version: INDEX_VERSION, entries: BTreeMap::new(), path, dirty: true }
+            }
+        }
+    }
+
+    /// Writes the index back to disk if it changed since it was loaded.
+    pub fn save(&mut self) -> Result<()> {
+        if self.dirty {
+            utils::write_atomic(&self.path, &serde_json::to_vec(self)?)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Returns the cached hash for `path` if its stat info still matches.
+    pub fn lookup(&self, path: &Path, metadata: &Metadata) -> Option<&str> {
+        let entry = self.entries.get(path)?;
+        let (size, secs, nanos, inode) = IndexEntry::stat(metadata)?;
+        let unchanged = entry.size == size
+            && entry.mtime_secs == secs
+            && entry.mtime_nanos == nanos
+            && entry.inode == inode;
+        unchanged.then_some(entry.hash.as_str())
+    }
+
+    /// Records the hash just computed for `path`.
+    pub fn insert(&mut self, path: &Path, metadata: &Metadata, hash: &str) {
+        let Some((size, mtime_secs, mtime_nanos, inode)) = IndexEntry::stat(metadata) else {
+            return;
+        };
+        let modified = UNIX_EPOCH + Duration::new(mtime_secs, mtime_nanos);
+        let is_racy = SystemTime::now()
+            .duration_since(modified)
+            .map_or(true, |age| age < RACY_WINDOW);
+        if is_racy {
+            self.remove(path);
+            return;
+        }
+        let entry = IndexEntry { size, mtime_secs, mtime_nanos, inode, hash: hash.to_string() };
+        if self.entries.get(path) != Some(&entry) {
+            self.entries.insert(path.to_path_buf(), entry);
+            self.dirty = true;
+        }
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if self.entries.remove(path).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Drops entries for paths that no longer satisfy `keep`.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+        let before = self.entries.len();
+        self.entries.retain(|path, _| keep(path));
+        if self.entries.len() != before {
+            self.dirty = true;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
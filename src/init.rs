@@ -0,0 +1,23 @@
+use crate::{repository::Repository, utils::HISTORY_DIR, Result};
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(about = "Creates an empty devcat repository in a directory.")]
+pub struct InitArgs {
+    /// The directory to initialize. Defaults to the current directory.
+    pub path: Option<PathBuf>,
+}
+
+pub fn run(args: InitArgs) -> Result<()> {
+    let path = args.path.unwrap_or_else(|| PathBuf::from("."));
+    let existed = path.join(HISTORY_DIR).is_dir();
+    let repo = Repository::init(&path)?;
+
+    if existed {
+        println!("✅ devcat repository already exists in {}", repo.history_dir().display());
+    } else {
+        println!("✅ Initialized empty devcat repository in {}", repo.history_dir().display());
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::{history::History, utils, Result, OutputArgs};
+use crate::{manifest::EntryKind, output, repository::Repository, Result, OutputArgs};
 use clap::Parser;
 use std::fmt::Write;
 use std::path::Path;
@@ -6,25 +6,37 @@ use std::path::Path;
 #[derive(Parser, Debug)]
 #[command(about = "Shows a list of all files in a specific snapshot.")]
 pub struct InspectArgs {
-    /// The snapshot ID to inspect.
-    #[arg(required = true)]
-    pub id: u32,
+    /// The snapshot to inspect: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`.
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub id: String,
     #[command(flatten)]
     pub output_args: OutputArgs,
 }
 
 pub fn run(args: InspectArgs) -> Result<()> {
-    let root_path = Path::new(".");
-    let history = History::load(root_path)?;
-    let snapshot = history.get_snapshot(args.id)?;
-
-    let manifest = utils::get_manifest_from_hash(root_path, &snapshot.manifest_hash)?;
+    let repo = Repository::discover(Path::new("."))?;
+    let snapshot = repo.snapshot(repo.resolve(&args.id)?)?;
+    let manifest = repo.read_manifest(&snapshot.manifest_hash)?;
 
     let mut output = String::new();
+    if !snapshot.metadata.is_empty() {
+        writeln!(&mut output, "Metadata for snapshot {}:", snapshot.id)?;
+        for (key, value) in &snapshot.metadata {
+            writeln!(&mut output, "  {}: {}", key, value)?;
+        }
+        writeln!(&mut output)?;
+    }
     writeln!(&mut output, "Files in snapshot {} ({}):", snapshot.id, snapshot.message)?;
-    for path in manifest.keys() {
-        writeln!(&mut output, "- {}", path.display())?;
+    for (path, entry) in &manifest {
+        match entry.kind {
+            EntryKind::File => writeln!(&mut output, "- {}", path.display())?,
+            EntryKind::Executable => writeln!(&mut output, "- {} (executable)", path.display())?,
+            EntryKind::Symlink => {
+                let target = repo.read_object(&entry.hash)?;
+                writeln!(&mut output, "- {} -> {}", path.display(), String::from_utf8_lossy(&target))?
+            }
+        }
     }
 
-    utils::handle_output(output, &args.output_args, "Snapshot content")
+    output::handle_output(output, &args.output_args, "Snapshot content")
 }
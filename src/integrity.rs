@@ -0,0 +1,113 @@
+use crate::error::Result;
+use crate::format;
+use crate::repository::Repository;
+use crate::manifest::Manifest;
+use crate::utils;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// The object is referenced but absent from the store.
+    Missing,
+    /// The object cannot be decoded, or its content does not hash to its name.
+    Corrupt,
+}
+
+/// A broken object reference found while checking a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub snapshot_id: u32,
+    /// The manifest path referencing the object, or `None` for the manifest itself.
+    pub path: Option<PathBuf>,
+    pub hash: String,
+    pub kind: ProblemKind,
+}
+
+/// The outcome of [`Repository::fsck`].
+#[derive(Debug, Default, Clone)]
+pub struct FsckReport {
+    /// The store format version found in `.devcat/FORMAT`. The check never upgrades it.
+    pub format_version: u32,
+    pub objects_checked: usize,
+    pub snapshots_checked: usize,
+    pub problems: Vec<Problem>,
+    /// Objects present in the store that no snapshot refers to.
+    pub unreferenced: Vec<String>,
+}
+
+impl FsckReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Re-hashes every stored object and checks each snapshot's references against the result.
+///
+/// Only whether each object is intact is kept; manifests are read again when checked.
+pub fn check(repo: &Repository) -> Result<FsckReport> {
+    let objects = repo.objects();
+    let history = repo.history()?;
+
+    let stored = objects.list()?;
+    let mut valid = BTreeMap::new();
+    for hash in &stored {
+        let intact = objects.read(hash).is_ok_and(|content| utils::hash_content(&content) == *hash);
+        valid.insert(hash.as_str(), intact);
+    }
+
+    let kind_of = |hash: &str| match valid.get(hash) {
+        None => Some(ProblemKind::Missing),
+        Some(false) => Some(ProblemKind::Corrupt),
+        Some(true) => None,
+    };
+
+    let mut problems = Vec::new();
+    let mut referenced = BTreeSet::new();
+    for snapshot in &history.snapshots {
+        referenced.insert(snapshot.manifest_hash.as_str());
+        let manifest = match kind_of(&snapshot.manifest_hash) {
+            Some(kind) => Err(kind),
+            None => objects
+                .read(&snapshot.manifest_hash)
+                .ok()
+                .and_then(|content| serde_json::from_slice::<Manifest>(&content).ok())
+                .ok_or(ProblemKind::Corrupt),
+        };
+        let manifest = match manifest {
+            Ok(manifest) => manifest,
+            Err(kind) => {
+                problems.push(Problem {
+                    snapshot_id: snapshot.id,
+                    path: None,
+                    hash: snapshot.manifest_hash.clone(),
+                    kind,
+                });
+                continue;
+            }
+        };
+
+        for (path, entry) in manifest {
+            if let Some(kind) = kind_of(&entry.hash) {
+                problems.push(Problem { snapshot_id: snapshot.id, path: Some(path), hash: entry.hash.clone(), kind });
+            }
+            if let Some((stored_hash, _)) = valid.get_key_value(entry.hash.as_str()) {
+                referenced.insert(*stored_hash);
+            }
+        }
+    }
+
+    let unreferenced = stored
+        .iter()
+        .filter(|hash| !referenced.contains(hash.as_str()))
+        .cloned()
+        .collect();
+
+    Ok(FsckReport {
+        format_version: format::read(&repo.history_dir())?,
+        objects_checked: stored.len(),
+        snapshots_checked: history.snapshots.len(),
+        problems,
+        unreferenced,
+    })
+}
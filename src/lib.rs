@@ -0,0 +1,36 @@
+//! devcat: filesystem snapshots and LLM context assembly for a development loop.
+//!
+//! The [`Repository`] type is the entry point for embedding devcat: it can
+//! initialize or discover a `.devcat` store, save snapshots, and read, diff,
+//! or revert them without going through the CLI.
+
+pub mod chunk;
+pub mod config;
+pub mod content;
+pub mod error;
+pub mod filter;
+pub mod format;
+pub mod history;
+pub mod index;
+pub mod integrity;
+pub mod manifest;
+pub mod metadata;
+pub mod object;
+pub mod render;
+pub mod repository;
+pub mod revision;
+pub mod store;
+pub mod tags;
+pub mod tokens;
+pub mod tree;
+pub mod utils;
+pub mod walk;
+
+pub use error::{Error, Result};
+pub use history::{History, Snapshot};
+pub use integrity::FsckReport;
+pub use repository::{FileChange, GcReport, Repository, RepositoryLock, RevertOutcome, RevertPlan};
+pub use store::ObjectStore;
+pub use tags::Tags;
+pub use manifest::{Entry, EntryKind, Manifest};
+pub use utils::SaveStatus;
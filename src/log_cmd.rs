@@ -1,37 +1,134 @@
-use crate::{history::History, utils, OutputArgs, Result};
+use crate::{output, repository::Repository, revision, OutputArgs, Result};
 use clap::Parser;
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::path::Path;
 
 #[derive(Parser, Debug)]
 #[command(about = "Shows the history of all saved snapshots.")]
 pub struct LogArgs {
+    /// Only show snapshots whose metadata has KEY set to VALUE. Repeat to require several.
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = crate::parse_meta)]
+    pub meta: Vec<(String, String)>,
+    /// Draw the parent links between snapshots, showing branches side by side.
+    #[arg(long)]
+    pub graph: bool,
     #[command(flatten)]
     pub output_args: OutputArgs,
 }
 
 pub fn run(args: LogArgs) -> Result<()> {
-    let root_path = Path::new(".");
-    let history = History::load(root_path)?;
+    let repo = Repository::discover(Path::new("."))?;
+    let history = repo.history()?;
+    let current = history.get_current().ok().map(|snapshot| snapshot.id);
+    let snapshots: Vec<_> = history
+        .snapshots
+        .iter()
+        .filter(|snapshot| {
+            args.meta
+                .iter()
+                .all(|(key, value)| snapshot.metadata.get(key) == Some(value))
+        })
+        .collect();
+    let tags = repo.tags()?;
     let mut output = String::new();
 
-    if history.snapshots.is_empty() {
+    if snapshots.is_empty() && !args.meta.is_empty() {
+        writeln!(&mut output, "No snapshots match the given --meta filters.")?;
+    } else if snapshots.is_empty() {
         writeln!(&mut output, "No snapshots found. Run `devcat save <message>` to create one.")?;
     } else {
-        writeln!(&mut output, "{:<3} {:<22} MESSAGE", "ID", "TIMESTAMP")?;
-        writeln!(&mut output, "{:-<3} {:-<22} {:-<50}", "", "", "")?;
+        if !args.graph {
+            writeln!(&mut output, "{:<3} {:<22} MESSAGE", "ID", "TIMESTAMP")?;
+            writeln!(&mut output, "{:-<3} {:-<22} {:-<50}", "", "", "")?;
+        }
 
-        for snapshot in history.snapshots.iter().rev() {
+        let shown: HashSet<u32> = snapshots.iter().map(|snapshot| snapshot.id).collect();
+        let mut graph = Graph::default();
+        for snapshot in snapshots.iter().rev() {
             let ts = snapshot.timestamp.format("%Y-%m-%d %H:%M:%S");
+            let mut labels = tags.names_for(snapshot.id);
+            if current == Some(snapshot.id) {
+                labels.insert(0, revision::CURRENT);
+            }
+            let decoration = if labels.is_empty() { String::new() } else { format!("[{}] ", labels.join(", ")) };
+            let prefix = if args.graph {
+                let parent = snapshot.parent.filter(|parent| shown.contains(parent));
+                graph.next(snapshot.id, parent, &mut output)?
+            } else {
+                String::new()
+            };
             writeln!(
                 &mut output,
-                "{:<3} {:<22} {}",
+                "{}{:<3} {:<22} {}{}",
+                prefix,
                 snapshot.id,
                 ts,
+                decoration,
                 snapshot.message.chars().take(50).collect::<String>()
             )?;
         }
     }
 
-    utils::handle_output(output, &args.output_args, "Log")
+    output::handle_output(output, &args.output_args, "Log")
+}
+
+/// Lays out `log --graph`, newest first. Each lane waits for the snapshot ID
+/// its branch continues with, so siblings end up in side-by-side columns.
+#[derive(Default)]
+struct Graph {
+    lanes: Vec<Option<u32>>,
+}
+
+impl Graph {
+    /// Places snapshot `id` and returns the graph prefix for its row. Lanes that
+    /// join this snapshot are closed first with a `|/` line written to `output`.
+    fn next(&mut self, id: u32, parent: Option<u32>, output: &mut String) -> Result<String> {
+        let waiting: Vec<usize> = (0..self.lanes.len()).filter(|&lane| self.lanes[lane] == Some(id)).collect();
+        let column = match waiting.first() {
+            Some(&lane) => lane,
+            None => match self.lanes.iter().position(Option::is_none) {
+                Some(free) => free,
+                None => {
+                    self.lanes.push(None);
+                    self.lanes.len() - 1
+                }
+            },
+        };
+
+        if waiting.len() > 1 {
+            let mut joined = vec![' '; self.lanes.len() * 2];
+            for (lane, expected) in self.lanes.iter().enumerate() {
+                if waiting[1..].contains(&lane) {
+                    joined[lane * 2 - 1] = '/';
+                } else if expected.is_some() {
+                    joined[lane * 2] = '|';
+                }
+            }
+            writeln!(output, "{}", joined.into_iter().collect::<String>().trim_end())?;
+            for &lane in &waiting[1..] {
+                self.lanes[lane] = None;
+            }
+            self.trim();
+        }
+
+        let row = self.render(|lane, expected| match expected {
+            _ if lane == column => "* ",
+            Some(_) => "| ",
+            None => "  ",
+        });
+        self.lanes[column] = parent;
+        self.trim();
+        Ok(row)
+    }
+
+    fn trim(&mut self) {
+        while self.lanes.last() == Some(&None) {
+            self.lanes.pop();
+        }
+    }
+
+    fn render(&self, cell: impl Fn(usize, Option<u32>) -> &'static str) -> String {
+        self.lanes.iter().enumerate().map(|(lane, expected)| cell(lane, *expected)).collect()
+    }
 }
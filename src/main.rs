@@ -2,23 +2,28 @@ use clap::{Args, Parser};
 use std::path::PathBuf;
 mod cat;
 mod clean;
-mod config;
 mod diff;
-mod error;
-mod history;
+mod fsck;
+mod gc;
+mod init;
 mod inspect;
 mod log_cmd;
+mod migrate;
 mod module;
+mod output;
 mod prune;
+mod restore;
 mod revert;
+mod reword;
 mod save;
+mod tag;
 mod trace;
-mod utils;
 mod watch;
 
+use devcat::{chunk, config, content, error, filter, format, integrity, manifest, metadata, render, repository, revision, tokens, tree, utils, walk};
 use log::{debug, LevelFilter};
 
-pub use error::Result;
+pub use devcat::Result;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -36,6 +41,10 @@ struct Cli {
 
     #[arg(short, long, global = true, action = clap::ArgAction::Count)]
     verbose: u8,
+
+    /// Run as if devcat was started in DIR instead of the current directory.
+    #[arg(short = 'C', long = "repo", value_name = "DIR", global = true)]
+    repo: Option<PathBuf>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -44,14 +53,122 @@ pub struct OutputArgs {
     pub output: Option<PathBuf>,
 }
 
+/// Which files a command visits; see [`devcat::walk`] and [`devcat::filter`].
 #[derive(Args, Debug, Clone)]
-pub struct ExcludeArgs {
+pub struct WalkArgs {
+    /// Skip paths matching this glob. Repeatable; wins over --include.
     #[arg(short = 'e', long)]
     pub exclude: Vec<String>,
+    /// Only visit files matching this glob. Repeatable; replaces `include` from `.devcatrc`.
+    #[arg(short = 'I', long)]
+    pub include: Vec<String>,
+    /// Visit hidden files and directories.
+    #[arg(long)]
+    pub hidden: bool,
+    /// Do not honour `.gitignore` and `.ignore` files.
+    #[arg(long)]
+    pub no_ignore: bool,
+}
+
+impl WalkArgs {
+    /// Combines these arguments with the settings from `config`.
+    pub fn options(&self, config: &config::Config) -> walk::WalkOptions {
+        walk::WalkOptions {
+            patterns: filter::Patterns::with_config(&self.include, &self.exclude, config),
+            hidden: self.hidden || config.hidden,
+            gitignore: config.gitignore && !self.no_ignore,
+        }
+    }
+}
+
+/// Loads `.devcatrc` from the repository containing `path`, or from `path` itself
+/// when it is not inside a repository.
+pub fn config_for(path: &std::path::Path) -> Result<config::Config> {
+    match repository::Repository::discover(path) {
+        Ok(repo) => config::load_config(repo.root()),
+        Err(error::Error::NotARepository(_)) => config::load_config(path),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FormatArgs {
+    /// How each file is framed in the output.
+    #[arg(long, value_enum, default_value_t = render::Format::Plain)]
+    pub format: render::Format,
+    /// Start with a tree of the included files, with their sizes and estimated tokens.
+    #[arg(long)]
+    pub tree: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ContentArgs {
+    /// Include text that is not valid UTF-8, replacing invalid bytes with `�`.
+    #[arg(long)]
+    pub lossy: bool,
+    /// List files larger than SIZE, e.g. `512K` or `2M`, without their content.
+    /// Overrides `max_file_size` from `.devcatrc`.
+    #[arg(long, value_name = "SIZE", value_parser = crate::parse_size)]
+    pub max_file_size: Option<u64>,
+}
+
+impl ContentArgs {
+    /// The size limit from these arguments, or else from `config`.
+    pub fn max_file_size(&self, config: &config::Config) -> Option<u64> {
+        self.max_file_size.or(config.max_file_size)
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TokenArgs {
+    /// Drop or truncate the largest files so the output fits in about N tokens.
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<usize>,
+    /// Characters per token used to estimate token counts.
+    #[arg(long, value_name = "RATIO", default_value_t = tokens::DEFAULT_CHARS_PER_TOKEN)]
+    pub chars_per_token: f64,
+    /// Print the estimated token count of every file to stderr.
+    #[arg(long)]
+    pub token_report: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SplitArgs {
+    /// Write the output as numbered parts of at most about N tokens each.
+    #[arg(long, value_name = "N", conflicts_with = "split_bytes")]
+    pub split_tokens: Option<usize>,
+    /// Write the output as numbered parts of at most N bytes each.
+    #[arg(long, value_name = "N")]
+    pub split_bytes: Option<usize>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CacheArgs {
+    /// Re-hash every file instead of reusing hashes cached in `.devcat/index`.
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct JobsArgs {
+    /// Number of threads used to walk and hash files. Defaults to the number of CPUs.
+    #[arg(short = 'j', long, default_value_t = 0, hide_default_value = true)]
+    pub jobs: usize,
+}
+
+/// Parses a `--max-file-size` argument.
+fn parse_size(size: &str) -> Result<u64> {
+    utils::parse_size(size)
+}
+
+/// Parses a `--meta key=value` argument.
+fn parse_meta(pair: &str) -> Result<(String, String)> {
+    metadata::parse_pair(pair)
 }
 
 #[derive(Parser, Debug)]
 enum Commands {
+    Init(init::InitArgs),
     Save(save::SaveArgs),
     Revert(revert::RevertArgs),
     Log(log_cmd::LogArgs),
@@ -62,6 +179,13 @@ enum Commands {
     Prune(prune::PruneArgs),
     Inspect(inspect::InspectArgs),
     Watch(watch::WatchArgs),
+    Migrate(migrate::MigrateArgs),
+    #[command(alias = "verify")]
+    Fsck(fsck::FsckArgs),
+    Gc(gc::GcArgs),
+    Tag(tag::TagArgs),
+    Reword(reword::RewordArgs),
+    Restore(restore::RestoreArgs),
 }
 
 fn main() {
@@ -76,7 +200,15 @@ fn main() {
 
     debug!("CLI arguments parsed successfully.");
 
+    if let Some(dir) = &cli.repo
+        && let Err(e) = std::env::set_current_dir(dir)
+    {
+        log::error!("Cannot change to directory {}: {}", dir.display(), e);
+        std::process::exit(1);
+    }
+
     let result = match cli.command {
+        Some(Commands::Init(args)) => init::run(args),
         Some(Commands::Save(args)) => save::run(args),
         Some(Commands::Revert(args)) => revert::run(args),
         Some(Commands::Log(args)) => log_cmd::run(args),
@@ -87,6 +219,12 @@ fn main() {
         Some(Commands::Prune(args)) => prune::run(args),
         Some(Commands::Inspect(args)) => inspect::run(args),
         Some(Commands::Watch(args)) => watch::run(args),
+        Some(Commands::Migrate(args)) => migrate::run(args),
+        Some(Commands::Fsck(args)) => fsck::run(args),
+        Some(Commands::Gc(args)) => gc::run(args),
+        Some(Commands::Tag(args)) => tag::run(args),
+        Some(Commands::Reword(args)) => reword::run(args),
+        Some(Commands::Restore(args)) => restore::run(args),
         None => cat::run(cli.cat_args),
     };
 
@@ -0,0 +1,136 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+/// Maps each path (relative to the repository root) to what was stored for it.
+pub type Manifest = BTreeMap<PathBuf, Entry>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    #[default]
+    File,
+    Executable,
+    /// The object holds the link target rather than file content.
+    Symlink,
+}
+
+/// One manifest entry: the content hash plus enough metadata to re-create the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredEntry")]
+pub struct Entry {
+    pub hash: String,
+    pub kind: EntryKind,
+    /// Unix permission bits, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+}
+
+/// The on-disk shapes of an entry. Manifests written before entries carried
+/// metadata map each path straight to its hash.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Hash(String),
+    Entry {
+        hash: String,
+        #[serde(default)]
+        kind: EntryKind,
+        #[serde(default)]
+        mode: Option<u32>,
+    },
+}
+
+impl From<StoredEntry> for Entry {
+    fn from(stored: StoredEntry) -> Self {
+        match stored {
+            StoredEntry::Hash(hash) => Entry { hash, kind: EntryKind::File, mode: None },
+            StoredEntry::Entry { hash, kind, mode } => Entry { hash, kind, mode },
+        }
+    }
+}
+
+impl Entry {
+    /// Describes the file behind `metadata` (from `symlink_metadata`) with content hash `hash`.
+    pub fn from_metadata(metadata: &Metadata, hash: String) -> Self {
+        if metadata.file_type().is_symlink() {
+            return Entry { hash, kind: EntryKind::Symlink, mode: None };
+        }
+        let mode = permission_bits(metadata);
+        let kind = if mode.is_some_and(|mode| mode & 0o111 != 0) {
+            EntryKind::Executable
+        } else {
+            EntryKind::File
+        };
+        Entry { hash, kind, mode }
+    }
+
+    /// Compares two entries, ignoring the mode when either side did not record one
+    /// (entries read from older manifests, or written on a platform without modes).
+    pub fn matches(&self, other: &Entry) -> bool {
+        let same_mode = match (self.mode, other.mode) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        self.hash == other.hash && self.kind == other.kind && same_mode
+    }
+}
+
+/// Reads the bytes that get hashed and stored for `path`: the file content, or the
+/// link target for symlinks.
+pub fn read_content(path: &Path, metadata: &Metadata) -> Result<Vec<u8>> {
+    if metadata.file_type().is_symlink() {
+        Ok(fs::read_link(path)?.into_os_string().into_encoded_bytes())
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+/// Re-creates `entry` at `path` from its stored `content`, replacing whatever is there.
+pub fn write_entry(path: &Path, entry: &Entry, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Never write through an existing symlink, and make room for a new one.
+    if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink() || entry.kind == EntryKind::Symlink) {
+        fs::remove_file(path)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        if entry.kind == EntryKind::Symlink {
+            std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(content), path)?;
+            return Ok(());
+        }
+        fs::write(path, content)?;
+        let mode = match (entry.mode, entry.kind) {
+            (Some(mode), _) => Some(mode),
+            (None, EntryKind::Executable) => Some(0o755),
+            (None, _) => None,
+        };
+        if let Some(mode) = mode {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+    }
+
+    #[cfg(not(unix))]
+    fs::write(path, content)?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn permission_bits(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn permission_bits(_metadata: &Metadata) -> Option<u32> {
+    None
+}
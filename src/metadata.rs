@@ -0,0 +1,55 @@
+//! Key/value metadata recorded with each snapshot.
+//!
+//! Every snapshot gets the keys below when they can be determined; `--meta key=value`
+//! pairs passed to `devcat save` are added on top and win on conflicts.
+//!
+//! ```text
+//! user            the user running devcat
+//! host            the machine's hostname
+//! git.branch      the checked-out branch of the enclosing git repository
+//! git.commit      the commit HEAD points at
+//! devcat.version  the devcat version that took the snapshot
+//! ```
+
+use crate::error::{Error, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+pub type Metadata = BTreeMap<String, String>;
+
+/// Gathers the automatic metadata for a snapshot of the repository at `root`.
+pub fn collect(root: &Path) -> Metadata {
+    let mut metadata = Metadata::new();
+    if let Some(user) = ["USER", "USERNAME"].iter().find_map(|var| std::env::var(var).ok()) {
+        metadata.insert("user".to_string(), user);
+    }
+    if let Ok(host) = gethostname::gethostname().into_string() {
+        metadata.insert("host".to_string(), host);
+    }
+    if let Some(branch) = git(root, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+        metadata.insert("git.branch".to_string(), branch);
+    }
+    if let Some(commit) = git(root, &["rev-parse", "--verify", "--quiet", "HEAD"]) {
+        metadata.insert("git.commit".to_string(), commit);
+    }
+    metadata.insert("devcat.version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+    metadata
+}
+
+/// Parses a `key=value` pair. Keys must be non-empty; values may be empty.
+pub fn parse_pair(pair: &str) -> Result<(String, String)> {
+    match pair.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(Error::InvalidMetadata(pair.to_string())),
+    }
+}
+
+/// Runs `git args` in `root`, returning its trimmed output when it succeeds.
+/// Missing git, no enclosing repository and unborn branches all yield `None`.
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(root).args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (output.status.success() && !stdout.is_empty()).then(|| stdout.to_string())
+}
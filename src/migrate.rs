@@ -0,0 +1,23 @@
+use crate::{format, repository::Repository, Result};
+use clap::Parser;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[command(about = "Upgrades the repository to the current store format and moves any flat objects into fan-out subdirectories.")]
+pub struct MigrateArgs {}
+
+pub fn run(_args: MigrateArgs) -> Result<()> {
+    let repo = Repository::discover(Path::new("."))?;
+    match repo.upgrade()? {
+        Some(from) => println!("✅ Upgraded repository format from v{} to v{}.", from, format::CURRENT),
+        None => println!("✅ Repository format is already v{}.", format::CURRENT),
+    }
+
+    // Stores already at the current format can still pick up flat objects from older devcat builds.
+    let _lock = repo.lock()?;
+    let moved = repo.objects().migrate()?;
+    if moved > 0 {
+        println!("✅ Migrated {} objects to the fan-out layout.", moved);
+    }
+    Ok(())
+}
@@ -1,8 +1,5 @@
-use crate::{utils, OutputArgs, Result, ExcludeArgs};
+use crate::{cat, output, ContentArgs, OutputArgs, Result, WalkArgs, FormatArgs, JobsArgs, SplitArgs, TokenArgs};
 use clap::Parser;
-use globset::{Glob, GlobSetBuilder};
-use ignore::WalkBuilder;
-use log::debug;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -12,50 +9,26 @@ pub struct ModuleArgs {
     #[arg(required = true)]
     path: PathBuf,
     #[command(flatten)]
-    pub exclude_args: ExcludeArgs,
+    pub walk_args: WalkArgs,
+    #[command(flatten)]
+    pub jobs_args: JobsArgs,
+    #[command(flatten)]
+    pub content_args: ContentArgs,
+    #[command(flatten)]
+    pub format_args: FormatArgs,
+    #[command(flatten)]
+    pub token_args: TokenArgs,
+    #[command(flatten)]
+    pub split_args: SplitArgs,
     #[command(flatten)]
     output_args: OutputArgs,
 }
 
 pub fn run(args: ModuleArgs) -> Result<()> {
-    let mut output = String::new();
-
-    let mut glob_builder = GlobSetBuilder::new();
-    for pattern in &args.exclude_args.exclude {
-        let glob = Glob::new(&format!("**/{}", pattern))?;
-        glob_builder.add(glob);
-    }
-    let exclude_set = glob_builder.build()?;
-
-    let walker = WalkBuilder::new(&args.path)
-        .follow_links(false)
-        .filter_entry(move |entry| {
-            if entry.path().starts_with("./.devcat") {
-                return false;
-            }
-            if exclude_set.is_match(entry.path()) {
-                debug!("Excluding path via --exclude: {}", entry.path().display());
-                return false;
-            }
-            true
-        })
-        .build();
-
-    for result in walker {
-        let entry = result?;
-        let path = entry.path();
-        if path.is_file() {
-            if utils::check_file_signature(path)? {
-                debug!("Skipping devcat output file: {}", path.display());
-                continue;
-            }
-            utils::append_file_content(
-                path,
-                path.strip_prefix(&args.path).unwrap_or(path),
-                &mut output,
-            )?;
-        }
-    }
+    let config = crate::config_for(&args.path)?;
+    let options = args.walk_args.options(&config);
+    let max_file_size = args.content_args.max_file_size(&config);
+    let files = cat::cat_from_workdir(&args.path, &options, args.jobs_args.jobs, args.content_args.lossy, max_file_size)?;
 
-    utils::handle_output(output, &args.output_args, "Module content")
+    output::assemble(files, &args.format_args, &args.token_args, &args.split_args, &args.output_args, "Module content")
 }
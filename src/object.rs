@@ -0,0 +1,66 @@
+use crate::error::{Error, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Prefix written before every encoded object. Files without it are legacy raw blobs.
+const OBJECT_MAGIC: &[u8] = b"\0dco";
+
+/// How an object's payload is stored on disk, recorded in the byte after [`OBJECT_MAGIC`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Raw = 0,
+    Deflate = 1,
+}
+
+impl Encoding {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Encoding::Raw),
+            1 => Some(Encoding::Deflate),
+            _ => None,
+        }
+    }
+}
+
+/// Compresses `content` for storage, falling back to `Raw` when deflate does not help.
+pub fn encode(content: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    let compressed = encoder.finish()?;
+
+    let (encoding, payload) = if compressed.len() < content.len() {
+        (Encoding::Deflate, compressed.as_slice())
+    } else {
+        (Encoding::Raw, content)
+    };
+
+    let mut stored = Vec::with_capacity(OBJECT_MAGIC.len() + 1 + payload.len());
+    stored.extend_from_slice(OBJECT_MAGIC);
+    stored.push(encoding as u8);
+    stored.extend_from_slice(payload);
+    Ok(stored)
+}
+
+/// Returns the original content of a stored object, accepting both encoded and legacy raw objects.
+pub fn decode(hash: &str, stored: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(rest) = stored.strip_prefix(OBJECT_MAGIC) else {
+        return Ok(stored);
+    };
+    let Some((&encoding, payload)) = rest.split_first() else {
+        return Err(Error::CorruptObject(hash.to_string()));
+    };
+
+    match Encoding::from_byte(encoding) {
+        Some(Encoding::Raw) => Ok(payload.to_vec()),
+        Some(Encoding::Deflate) => {
+            let mut content = Vec::new();
+            DeflateDecoder::new(payload)
+                .read_to_end(&mut content)
+                .map_err(|_| Error::CorruptObject(hash.to_string()))?;
+            Ok(content)
+        }
+        None => Err(Error::CorruptObject(hash.to_string())),
+    }
+}
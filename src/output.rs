@@ -0,0 +1,167 @@
+use crate::chunk::{self, Limit};
+use crate::render::{Body, ContextFile, Document, Formatter};
+use crate::tokens::{self, TokenEstimator};
+use crate::utils::{self, FILE_SIGNATURE};
+use crate::{tree, FormatArgs, OutputArgs, Result, SplitArgs, TokenArgs};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn handle_output(content: String, output_args: &OutputArgs, context_name: &str) -> Result<()> {
+    if let Some(path) = &output_args.output {
+        debug!("Writing output to file: {}", path.display());
+        let final_content = format!("{}\n{}", FILE_SIGNATURE, content);
+        fs::write(path, final_content)?;
+        println!("✅ {} context saved to {}", context_name, path.display());
+    } else {
+        print!("{}", content);
+    }
+    Ok(())
+}
+
+/// Renders `document` with `formatter` and writes it to `--output` (signed, so later
+/// walks skip it) or to stdout.
+pub fn write_document(
+    formatter: &dyn Formatter,
+    document: Document,
+    output_args: &OutputArgs,
+    context_name: &str,
+) -> Result<()> {
+    if let Some(path) = &output_args.output {
+        debug!("Writing output to file: {}", path.display());
+        fs::write(path, formatter.document(&Document { signed: true, ..document })?)?;
+        println!("✅ {} context saved to {}", context_name, path.display());
+    } else {
+        print!("{}", formatter.document(&document)?);
+    }
+    Ok(())
+}
+
+/// Fits `files` into the `--max-tokens` budget, reports token estimates on stderr,
+/// and writes what is left in the selected format, split into parts if requested.
+/// With `--tree`, the output starts with a tree of exactly the files that follow.
+pub fn assemble(
+    files: Vec<ContextFile>,
+    format_args: &FormatArgs,
+    token_args: &TokenArgs,
+    split_args: &SplitArgs,
+    output_args: &OutputArgs,
+    context_name: &str,
+) -> Result<()> {
+    let skipped: Vec<_> = files
+        .iter()
+        .filter_map(|file| match &file.body {
+            Body::Skipped(reason) => Some((file.path.display().to_string(), reason.clone())),
+            Body::Content(_) => None,
+        })
+        .collect();
+    if !skipped.is_empty() {
+        eprintln!("⏭️ {} files are listed without their content:", skipped.len());
+        for (path, reason) in &skipped {
+            eprintln!("- {}: {}", path, reason);
+        }
+    }
+
+    let formatter = format_args.format.formatter();
+    let estimator = TokenEstimator::new(token_args.chars_per_token);
+    let tree_size = |files: &[ContextFile], tokens: &[usize]| -> Result<(String, usize)> {
+        let tree = tree::render(files, tokens);
+        let mut rendered = String::new();
+        formatter.tree(&tree, &mut rendered)?;
+        Ok((tree, estimator.estimate(&rendered)))
+    };
+
+    // The tree of every file is at least as large as the tree of the files that end up
+    // fitting, so holding back its size keeps the final output within the budget.
+    let mut max_tokens = token_args.max_tokens;
+    if format_args.tree {
+        let estimates = files.iter().map(|file| estimator.estimate_file(file, formatter)).collect::<Result<Vec<_>>>()?;
+        let (_, tree_tokens) = tree_size(&files, &estimates)?;
+        max_tokens = max_tokens.map(|max| max.saturating_sub(tree_tokens));
+    }
+    let budgeted = tokens::fit(files, max_tokens, &estimator, formatter)?;
+    let (tree, tree_tokens) = if format_args.tree {
+        let estimates: Vec<_> = budgeted.included.iter().map(|file| file.tokens).collect();
+        let (tree, tokens) = tree_size(&budgeted.files, &estimates)?;
+        (Some(tree), tokens)
+    } else {
+        (None, 0)
+    };
+
+    if token_args.token_report {
+        eprintln!("📊 Estimated tokens per file:");
+        for file in &budgeted.included {
+            eprintln!("{:>8}  {}", file.tokens, file.path.display());
+        }
+    }
+    for file in &budgeted.truncated {
+        eprintln!("✂️ Truncated {} (~{} tokens) to fit --max-tokens.", file.path.display(), file.tokens);
+    }
+    if !budgeted.dropped.is_empty() {
+        eprintln!("⚠️ Left out {} files to fit --max-tokens:", budgeted.dropped.len());
+        for file in &budgeted.dropped {
+            eprintln!("- {} (~{} tokens)", file.path.display(), file.tokens);
+        }
+    }
+    eprintln!(
+        "📊 ~{} tokens (estimated) across {} files.",
+        budgeted.total_tokens() + tree_tokens,
+        budgeted.files.len()
+    );
+
+    let limit = match (split_args.split_tokens, split_args.split_bytes) {
+        (Some(tokens), _) => Limit::Tokens(tokens, estimator),
+        (None, Some(bytes)) => Limit::Bytes(bytes),
+        (None, None) => {
+            let document = Document { files: &budgeted.files, tree: tree.as_deref(), ..Document::default() };
+            return write_document(formatter, document, output_args, context_name);
+        }
+    };
+    // The tree goes at the start of the first part only.
+    let framing = Document { signed: true, part: Some((999, 999)), ..Document::default() };
+    let reserved = limit.measure(&formatter.document(&framing)?);
+    let first_reserved = limit.measure(&formatter.document(&Document { tree: tree.as_deref(), ..framing })?);
+    if tree.is_some() && first_reserved >= limit.capacity() {
+        eprintln!("⚠️ The file tree alone exceeds the split limit, so part 1 will be larger than requested.");
+    }
+    let mut parts = chunk::split(budgeted.files, limit, first_reserved, reserved, formatter)?;
+    if parts.is_empty() {
+        parts.push(Vec::new());
+    }
+
+    let base = output_args.output.clone().unwrap_or_else(|| PathBuf::from("context.txt"));
+    let count = parts.len();
+    for (index, part) in parts.iter().enumerate() {
+        let path = part_path(&base, index + 1);
+        debug!("Writing part {} of {} to {}", index + 1, count, path.display());
+        let part_tree = if index == 0 { tree.as_deref() } else { None };
+        let document = Document { files: part, tree: part_tree, signed: true, part: Some((index + 1, count)) };
+        fs::write(&path, formatter.document(&document)?)?;
+    }
+    // Parts left over from an earlier, longer run would otherwise read as part of this one.
+    let mut stale = count + 1;
+    while utils::check_file_signature(&part_path(&base, stale))? {
+        fs::remove_file(part_path(&base, stale))?;
+        stale += 1;
+    }
+
+    if count == 1 {
+        println!("✅ {} context saved to {} (1 part)", context_name, part_path(&base, 1).display());
+    } else {
+        println!(
+            "✅ {} context saved to {} parts: {} … {}",
+            context_name,
+            count,
+            part_path(&base, 1).display(),
+            part_path(&base, count).display()
+        );
+    }
+    Ok(())
+}
+
+/// Turns `context.txt` into `context.001.txt` for part 1.
+fn part_path(base: &Path, part: usize) -> PathBuf {
+    let stem = base.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "context".to_string());
+    let extension = base.extension().map(|ext| ext.to_string_lossy().into_owned()).unwrap_or_else(|| "txt".to_string());
+    base.with_file_name(format!("{}.{:03}.{}", stem, part, extension))
+}
@@ -1,11 +1,10 @@
-use crate::{history::History, utils, Result};
+use crate::{repository::Repository, Result};
 use clap::Parser;
 use std::collections::HashSet;
-use std::fs;
 use std::path::Path;
 
 #[derive(Parser, Debug)]
-#[command(about = "Deletes old snapshots, keeping a specified number of recent ones.")]
+#[command(about = "Deletes old snapshots, keeping a specified number of recent ones, any tagged ones, and the current one.")]
 pub struct PruneArgs {
     /// The number of recent snapshots to keep.
     #[arg(long, default_value_t = 10)]
@@ -13,45 +12,54 @@ pub struct PruneArgs {
 }
 
 pub fn run(args: PruneArgs) -> Result<()> {
-    let root_path = Path::new(".");
-    let mut history = History::load(root_path)?;
+    let repo = Repository::discover(Path::new("."))?;
+    let _lock = repo.lock()?;
+    let history = repo.history()?;
     
     if history.snapshots.len() <= args.keep {
         println!("Number of snapshots ({}) is less than or equal to the number to keep ({}). Nothing to prune.", history.snapshots.len(), args.keep);
         return Ok(());
     }
 
-    let original_len = history.snapshots.len();
-    let snapshots_to_keep = history.snapshots.split_off(original_len - args.keep);
-    let snapshots_to_delete = history.snapshots;
+    let tags = repo.tags()?;
+    // The current snapshot is the baseline for the next save and `diff`, so it always stays.
+    let current = history.get_current().ok().map(|snapshot| snapshot.id);
+    let cutoff = history.snapshots.len() - args.keep;
+    let (snapshots_to_keep, snapshots_to_delete): (Vec<_>, Vec<_>) = history
+        .snapshots
+        .into_iter()
+        .enumerate()
+        .partition(|(position, snapshot)| {
+            *position >= cutoff || Some(snapshot.id) == current || !tags.names_for(snapshot.id).is_empty()
+        });
+    let snapshots_to_keep: Vec<_> = snapshots_to_keep.into_iter().map(|(_, snapshot)| snapshot).collect();
+    let snapshots_to_delete: Vec<_> = snapshots_to_delete.into_iter().map(|(_, snapshot)| snapshot).collect();
     
     let mut kept_hashes = HashSet::new();
     for snapshot in &snapshots_to_keep {
         kept_hashes.insert(snapshot.manifest_hash.clone());
-        if let Ok(manifest) = utils::get_manifest_from_hash(root_path, &snapshot.manifest_hash) {
-            for hash in manifest.values() {
-                kept_hashes.insert(hash.clone());
+        if let Ok(manifest) = repo.read_manifest(&snapshot.manifest_hash) {
+            for entry in manifest.values() {
+                kept_hashes.insert(entry.hash.clone());
             }
         }
     }
     
-    let objects_dir = root_path.join(".devcat").join("objects");
-    if objects_dir.exists() {
-        for snapshot in &snapshots_to_delete {
-            if !kept_hashes.contains(&snapshot.manifest_hash) {
-                let _ = fs::remove_file(objects_dir.join(&snapshot.manifest_hash));
-            }
-            if let Ok(manifest) = utils::get_manifest_from_hash(root_path, &snapshot.manifest_hash) {
-                for hash in manifest.values() {
-                    if !kept_hashes.contains(hash) {
-                        let _ = fs::remove_file(objects_dir.join(hash));
-                    }
+    let objects = repo.objects();
+    for snapshot in &snapshots_to_delete {
+        if let Ok(manifest) = repo.read_manifest(&snapshot.manifest_hash) {
+            for entry in manifest.values() {
+                if !kept_hashes.contains(&entry.hash) {
+                    let _ = objects.remove(&entry.hash);
                 }
             }
         }
+        if !kept_hashes.contains(&snapshot.manifest_hash) {
+            let _ = objects.remove(&snapshot.manifest_hash);
+        }
     }
     
-    let mut final_history = History::load(root_path)?;
+    let mut final_history = repo.history()?;
     final_history.snapshots = snapshots_to_keep;
     final_history.save()?;
     
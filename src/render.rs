@@ -0,0 +1,292 @@
+//! Rendering of files into a single context document for `cat`, `module` and `trace`.
+//!
+//! Commands collect [`ContextFile`]s and hand them to the [`Formatter`] for the
+//! selected [`Format`], so the framing around each file lives in one place.
+
+use crate::content::{self, Skip};
+use crate::error::Result;
+use crate::utils;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How files are framed in the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// `--- START FILE: path ---` / `--- END FILE: path ---` markers.
+    #[default]
+    Plain,
+    /// A heading per file followed by a fenced code block with a language tag.
+    Markdown,
+    /// `<file path="...">` elements. Content is included verbatim, not escaped.
+    Xml,
+    /// A `{ "files" }` object holding an array of `{ "path", "line", "content" | "skipped" }`
+    /// objects, plus `"tree"` with `--tree`, `"part"` and `"parts"` when split, and a
+    /// leading `"devcat"` marker when written to a file.
+    Json,
+}
+
+impl Format {
+    pub fn formatter(self) -> &'static dyn Formatter {
+        match self {
+            Format::Plain => &Plain,
+            Format::Markdown => &Markdown,
+            Format::Xml => &Xml,
+            Format::Json => &Json,
+        }
+    }
+}
+
+/// One file (or excerpt of a file) to include in the output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContextFile {
+    pub path: PathBuf,
+    /// Where an excerpt sits in the file: the line `trace` centres on, or the first
+    /// line of a piece of a file split across output parts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(flatten)]
+    pub body: Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Body {
+    /// The text to include.
+    Content(String),
+    /// Why the content was left out, e.g. `Skipped binary file: PNG image (12.0 KiB)`.
+    Skipped(String),
+}
+
+impl ContextFile {
+    /// Reads `full_path` as text, labelling it with `relative_path`. Binary and
+    /// unreadable files become [`Body::Skipped`]; see [`content::decode`] for `lossy`.
+    pub fn read(full_path: &Path, relative_path: &Path, lossy: bool) -> Self {
+        let body = match fs::read(full_path) {
+            Ok(bytes) => Body::decode(bytes, lossy),
+            Err(e) => Body::Skipped(Skip::Unreadable(e.to_string()).describe(None)),
+        };
+        ContextFile { path: relative_path.to_path_buf(), line: None, body }
+    }
+
+    /// A file left out for `reason`.
+    pub fn skipped(path: PathBuf, reason: Skip, size: u64) -> Self {
+        ContextFile { path, line: None, body: Body::Skipped(reason.describe(Some(size))) }
+    }
+}
+
+impl Body {
+    /// Decodes `bytes` as text, or describes why they were skipped.
+    pub fn decode(bytes: Vec<u8>, lossy: bool) -> Self {
+        let size = bytes.len() as u64;
+        match content::decode(bytes, lossy) {
+            Ok(text) => Body::Content(text),
+            Err(reason) => Body::Skipped(reason.describe(Some(size))),
+        }
+    }
+}
+
+/// Everything that goes into one rendered output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Document<'a> {
+    pub files: &'a [ContextFile],
+    /// The `--tree` overview drawn by [`crate::tree::render`].
+    pub tree: Option<&'a str>,
+    /// Mark the output as written by devcat, so later walks skip it. See
+    /// [`utils::check_file_signature`].
+    pub signed: bool,
+    /// This document's position among the parts of a split output, as `(part, count)`.
+    pub part: Option<(usize, usize)>,
+}
+
+/// Frames a list of files into one document.
+pub trait Formatter {
+    /// Renders a single file.
+    fn file(&self, file: &ContextFile, output: &mut String) -> Result<()>;
+
+    /// Renders the `--tree` overview drawn by [`crate::tree::render`].
+    fn tree(&self, tree: &str, output: &mut String) -> Result<()>;
+
+    /// What [`Formatter::document`] puts between two files, on top of their own rendering.
+    fn separator(&self) -> &'static str {
+        ""
+    }
+
+    /// Renders the signature and part lines, the tree if any, then every file in order.
+    ///
+    /// The result must be the framing of an empty document plus each file as rendered
+    /// by [`Formatter::file`] and [`Formatter::separator`], so parts can be sized file
+    /// by file. Formats that need a surrounding structure override this.
+    fn document(&self, document: &Document) -> Result<String> {
+        let mut output = String::new();
+        if document.signed {
+            writeln!(output, "{}", utils::FILE_SIGNATURE)?;
+        }
+        if let Some((part, count)) = document.part {
+            writeln!(output, "// part {} of {}", part, count)?;
+        }
+        if let Some(tree) = document.tree {
+            self.tree(tree, &mut output)?;
+        }
+        for file in document.files {
+            self.file(file, &mut output)?;
+        }
+        Ok(output)
+    }
+
+}
+
+struct Plain;
+
+impl Formatter for Plain {
+    fn file(&self, file: &ContextFile, output: &mut String) -> Result<()> {
+        let path = file.path.display();
+        match file.line {
+            Some(line) => writeln!(output, "--- START FILE: {} (line {}) ---", path, line)?,
+            None => writeln!(output, "--- START FILE: {} ---", path)?,
+        }
+        match &file.body {
+            Body::Content(content) => writeln!(output, "{}", content)?,
+            Body::Skipped(reason) => writeln!(output, "[{}]", reason)?,
+        }
+        writeln!(output, "--- END FILE: {} ---\n", path)?;
+        Ok(())
+    }
+
+    fn tree(&self, tree: &str, output: &mut String) -> Result<()> {
+        writeln!(output, "--- FILE TREE ---\n{}--- END FILE TREE ---\n", tree)?;
+        Ok(())
+    }
+}
+
+struct Markdown;
+
+impl Formatter for Markdown {
+    fn file(&self, file: &ContextFile, output: &mut String) -> Result<()> {
+        match file.line {
+            Some(line) => writeln!(output, "## `{}` (line {})\n", file.path.display(), line)?,
+            None => writeln!(output, "## `{}`\n", file.path.display())?,
+        }
+        match &file.body {
+            Body::Content(content) => {
+                // The fence must be longer than any backtick run inside the content.
+                let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+                let fence = "`".repeat(longest_run.max(2) + 1);
+                writeln!(output, "{}{}", fence, language_for(&file.path))?;
+                writeln!(output, "{}", content.strip_suffix('\n').unwrap_or(content))?;
+                writeln!(output, "{}\n", fence)?;
+            }
+            Body::Skipped(reason) => writeln!(output, "_{}_\n", reason)?,
+        }
+        Ok(())
+    }
+
+    fn tree(&self, tree: &str, output: &mut String) -> Result<()> {
+        writeln!(output, "## File tree\n\n```text\n{}```\n", tree)?;
+        Ok(())
+    }
+}
+
+struct Xml;
+
+impl Formatter for Xml {
+    fn file(&self, file: &ContextFile, output: &mut String) -> Result<()> {
+        let path = escape_attribute(&file.path.to_string_lossy());
+        let line = file.line.map(|line| format!(" line=\"{}\"", line)).unwrap_or_default();
+        match &file.body {
+            Body::Content(content) => {
+                writeln!(output, "<file path=\"{}\"{}>", path, line)?;
+                writeln!(output, "{}", content.strip_suffix('\n').unwrap_or(content))?;
+                writeln!(output, "</file>\n")?;
+            }
+            Body::Skipped(reason) => {
+                writeln!(output, "<file path=\"{}\"{} skipped=\"{}\"/>\n", path, line, escape_attribute(reason))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tree(&self, tree: &str, output: &mut String) -> Result<()> {
+        writeln!(output, "<tree>\n{}</tree>\n", tree)?;
+        Ok(())
+    }
+}
+
+struct Json;
+
+impl Formatter for Json {
+    fn file(&self, file: &ContextFile, output: &mut String) -> Result<()> {
+        output.push_str(&serde_json::to_string(file)?);
+        Ok(())
+    }
+
+    fn tree(&self, tree: &str, output: &mut String) -> Result<()> {
+        writeln!(output, "{}", serde_json::to_string(tree)?)?;
+        Ok(())
+    }
+
+    fn separator(&self) -> &'static str {
+        ",\n"
+    }
+
+    /// A `{ "devcat", "part", "parts", "tree", "files" }` object with one file per line.
+    /// Only `"files"` is always present. JSON has no comments to carry the signature, so
+    /// signed output starts with [`utils::JSON_SIGNATURE`] instead.
+    fn document(&self, document: &Document) -> Result<String> {
+        let mut files = String::from("[");
+        for (index, file) in document.files.iter().enumerate() {
+            files.push_str(if index == 0 { "\n" } else { self.separator() });
+            self.file(file, &mut files)?;
+        }
+        files.push_str(if document.files.is_empty() { "]" } else { "\n]" });
+
+        let mut fields = Vec::new();
+        if document.signed {
+            fields.push(format!("\"devcat\": {}", serde_json::to_string(utils::OUTPUT_MARKER)?));
+        }
+        if let Some((part, count)) = document.part {
+            fields.push(format!("\"part\": {}, \"parts\": {}", part, count));
+        }
+        if let Some(tree) = document.tree {
+            fields.push(format!("\"tree\": {}", serde_json::to_string(tree)?));
+        }
+        fields.push(format!("\"files\": {}", files));
+        Ok(format!("{{{}}}\n", fields.join(",\n")))
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Picks a Markdown code fence language from the file extension.
+fn language_for(path: &Path) -> String {
+    let Some(extension) = path.extension().map(|e| e.to_string_lossy().to_lowercase()) else {
+        return String::new();
+    };
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "rb" => "ruby",
+        "sh" | "bash" => "bash",
+        "yml" | "yaml" => "yaml",
+        "md" => "markdown",
+        "txt" => "text",
+        "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "kt" | "kts" => "kotlin",
+        other => other,
+    };
+    language.to_string()
+}
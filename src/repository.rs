@@ -0,0 +1,529 @@
+use crate::config;
+use crate::error::{Error, Result};
+use crate::filter::Patterns;
+use crate::walk::WalkOptions;
+use crate::format;
+use crate::history::{History, Snapshot};
+use crate::index::Index;
+use crate::integrity::{self, FsckReport};
+use crate::manifest::{self, Entry, Manifest};
+use crate::metadata::{self, Metadata};
+use crate::revision;
+use crate::store::ObjectStore;
+use crate::tags::Tags;
+use crate::utils::{self, SaveStatus, HISTORY_DIR};
+use globset::Glob;
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// A devcat repository: the directory that contains the `.devcat` history.
+#[derive(Debug, Clone)]
+pub struct Repository {
+    root: PathBuf,
+    use_index: bool,
+    jobs: usize,
+}
+
+/// Holds the advisory lock on `.devcat/lock`; released when dropped.
+#[derive(Debug)]
+pub struct RepositoryLock {
+    _file: File,
+}
+
+/// The outcome of [`Repository::gc`].
+#[derive(Debug, Default, Clone)]
+pub struct GcReport {
+    /// Hashes of the unreachable objects that were (or, in a dry run, would be) deleted.
+    pub removed: Vec<String>,
+    /// Temporary files left in `objects/` by interrupted writes, deleted along with them.
+    pub temp_files: Vec<PathBuf>,
+    pub reclaimed_bytes: u64,
+}
+
+/// The file operations needed to turn the working tree into a snapshot.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RevertPlan {
+    /// Paths in the snapshot that are missing from the working tree.
+    pub create: Vec<PathBuf>,
+    /// Paths whose working-tree content, kind or mode differs from the snapshot.
+    pub modify: Vec<PathBuf>,
+    /// Paths in the working tree that the snapshot does not contain.
+    pub delete: Vec<PathBuf>,
+}
+
+impl RevertPlan {
+    fn new(working: &Manifest, target: &Manifest) -> Self {
+        let mut plan = RevertPlan::default();
+        for change in diff_manifests(working, target) {
+            match (change.old, change.new) {
+                (None, Some(_)) => plan.create.push(change.path),
+                (Some(_), Some(_)) => plan.modify.push(change.path),
+                (Some(_), None) => plan.delete.push(change.path),
+                (None, None) => {}
+            }
+        }
+        plan
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.modify.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// The outcome of [`Repository::revert`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevertOutcome {
+    pub plan: RevertPlan,
+    /// The pre-revert snapshot taken before any file was touched.
+    pub backup: SaveStatus,
+}
+
+/// A single path whose entry differs between two manifests.
+///
+/// `None` on either side means the path does not exist there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub old: Option<Entry>,
+    pub new: Option<Entry>,
+}
+
+impl FileChange {
+    /// True when the path exists on both sides with the same content and only its kind or mode changed.
+    pub fn is_mode_change(&self) -> bool {
+        matches!((&self.old, &self.new), (Some(old), Some(new)) if old.hash == new.hash)
+    }
+}
+
+impl Repository {
+    /// Opens the repository whose `.devcat` directory lives directly in `root`.
+    pub fn open(root: &Path) -> Result<Self> {
+        let root = root.canonicalize()?;
+        if !root.join(HISTORY_DIR).is_dir() {
+            return Err(Error::NotARepository(root));
+        }
+        format::check(&root.join(HISTORY_DIR))?;
+        Ok(Repository { root, use_index: true, jobs: 0 })
+    }
+
+    /// Searches `start` and each of its parents for an existing `.devcat` directory.
+    pub fn discover(start: &Path) -> Result<Self> {
+        let start = start.canonicalize()?;
+        for dir in start.ancestors() {
+            if dir.join(HISTORY_DIR).is_dir() {
+                debug!("Discovered devcat repository at {}", dir.display());
+                format::check(&dir.join(HISTORY_DIR))?;
+                return Ok(Repository { root: dir.to_path_buf(), use_index: true, jobs: 0 });
+            }
+        }
+        Err(Error::NotARepository(start))
+    }
+
+    /// Creates the `.devcat` layout in `root`. Existing repositories are left untouched.
+    pub fn init(root: &Path) -> Result<Self> {
+        let history_dir = root.join(HISTORY_DIR);
+        let objects_dir = history_dir.join("objects");
+        if !history_dir.exists() {
+            debug!("Creating history directory at {}", objects_dir.display());
+            fs::create_dir_all(&objects_dir)?;
+            format::write(&history_dir, format::CURRENT)?;
+        } else {
+            format::check(&history_dir)?;
+            fs::create_dir_all(&objects_dir)?;
+        }
+        Ok(Repository { root: root.canonicalize()?, use_index: true, jobs: 0 })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn history_dir(&self) -> PathBuf {
+        self.root.join(HISTORY_DIR)
+    }
+
+    pub fn objects(&self) -> ObjectStore {
+        ObjectStore::new(self.history_dir().join("objects"))
+    }
+
+    /// Controls whether working-tree scans reuse hashes from `.devcat/index`. Enabled by default.
+    pub fn set_use_index(&mut self, use_index: bool) {
+        self.use_index = use_index;
+    }
+
+    /// Sets how many threads walk and hash the working tree. `0` picks a count automatically.
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs;
+    }
+
+    /// Takes the repository-wide write lock, waiting for other devcat processes to release it.
+    ///
+    /// Stores in an older format are upgraded first, so writers only ever see [`format::CURRENT`].
+    pub fn lock(&self) -> Result<RepositoryLock> {
+        let lock = self.acquire_lock()?;
+        if let Some(from) = format::upgrade(&self.history_dir())? {
+            warn!("Upgraded repository format from v{} to v{}.", from, format::CURRENT);
+        }
+        Ok(lock)
+    }
+
+    /// Upgrades the store to [`format::CURRENT`], returning the version it started at
+    /// if anything had to change.
+    pub fn upgrade(&self) -> Result<Option<u32>> {
+        let _lock = self.acquire_lock()?;
+        format::upgrade(&self.history_dir())
+    }
+
+    fn acquire_lock(&self) -> Result<RepositoryLock> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.history_dir().join("lock"))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                warn!("Waiting for another devcat process to release the repository lock...");
+                file.lock()?;
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        Ok(RepositoryLock { _file: file })
+    }
+
+    pub fn history(&self) -> Result<History> {
+        History::load(&self.root)
+    }
+
+    /// Returns all snapshots, oldest first.
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        Ok(self.history()?.snapshots)
+    }
+
+    pub fn tags(&self) -> Result<Tags> {
+        Tags::load(&self.root)
+    }
+
+    /// Turns a snapshot reference into a snapshot ID. See [`revision`] for the accepted syntax.
+    pub fn resolve(&self, spec: &str) -> Result<u32> {
+        revision::resolve(spec, &self.history()?, &self.tags()?)
+    }
+
+    /// Attaches tag `name` to snapshot `id`, moving an existing tag only when `force` is set.
+    pub fn tag(&self, name: &str, id: u32, force: bool) -> Result<()> {
+        let _lock = self.lock()?;
+        self.history()?.get_snapshot(id)?;
+        let mut tags = self.tags()?;
+        tags.set(name, id, force)?;
+        tags.save()
+    }
+
+    /// Deletes tag `name` and returns the snapshot ID it pointed at.
+    pub fn delete_tag(&self, name: &str) -> Result<u32> {
+        let _lock = self.lock()?;
+        let mut tags = self.tags()?;
+        let id = tags.remove(name)?;
+        tags.save()?;
+        Ok(id)
+    }
+
+    pub fn snapshot(&self, id: u32) -> Result<Snapshot> {
+        self.history()?.get_snapshot(id).cloned()
+    }
+
+    /// Snapshots the working tree files selected by `options`.
+    ///
+    /// `extra` is recorded alongside the automatic metadata and overrides it on conflicts.
+    pub fn save(&self, message: &str, options: &WalkOptions, extra: &Metadata) -> Result<SaveStatus> {
+        let _lock = self.lock()?;
+        self.save_locked(message, options, extra)
+    }
+
+    /// Does the work of [`Repository::save`] for callers already holding the lock.
+    fn save_locked(&self, message: &str, options: &WalkOptions, extra: &Metadata) -> Result<SaveStatus> {
+        let mut history = self.history()?;
+        let manifest = self.working_manifest(options)?;
+        let manifest_content = serde_json::to_vec(&manifest)?;
+        let current_manifest_hash = utils::hash_content(&manifest_content);
+
+        // Compare entries rather than manifest hashes: manifests from older formats
+        // encode the same entries differently.
+        if let Ok(current) = history.get_current()
+            && (current.manifest_hash == current_manifest_hash
+                || diff_manifests(&self.read_manifest(&current.manifest_hash)?, &manifest).is_empty())
+        {
+            return Ok(SaveStatus::NoChanges);
+        }
+
+        self.store_manifest(&manifest, &current_manifest_hash, &manifest_content)?;
+
+        let mut metadata = metadata::collect(&self.root);
+        metadata.extend(extra.iter().map(|(key, value)| (key.clone(), value.clone())));
+        history.add_snapshot(message.to_string(), current_manifest_hash, options, metadata);
+        history.save()?;
+
+        let latest = history.get_latest()?;
+        Ok(SaveStatus::Saved {
+            id: latest.id,
+            message: latest.message.clone(),
+        })
+    }
+
+    /// Replaces the current snapshot with the working tree as it is now, keeping its ID.
+    /// The message is kept unless a new one is given.
+    ///
+    /// Only a snapshot that nothing has been saved on top of can be amended.
+    pub fn amend(&self, message: Option<&str>, options: &WalkOptions, extra: &Metadata) -> Result<SaveStatus> {
+        let _lock = self.lock()?;
+        let mut history = self.history()?;
+        let id = history.get_current()?.id;
+        if history.snapshots.iter().any(|s| s.parent == Some(id)) {
+            return Err(Error::SnapshotHasChildren(id));
+        }
+
+        let manifest = self.working_manifest(options)?;
+        let manifest_content = serde_json::to_vec(&manifest)?;
+        let manifest_hash = utils::hash_content(&manifest_content);
+        let unchanged = diff_manifests(&self.manifest(id)?, &manifest).is_empty();
+        let snapshot = history.get_snapshot_mut(id)?;
+        if unchanged && message.is_none_or(|m| m == snapshot.message) {
+            return Ok(SaveStatus::NoChanges);
+        }
+
+        self.store_manifest(&manifest, &manifest_hash, &manifest_content)?;
+
+        snapshot.timestamp = chrono::Utc::now();
+        snapshot.manifest_hash = manifest_hash;
+        snapshot.record_walk_options(options);
+        snapshot.metadata.extend(metadata::collect(&self.root));
+        snapshot.metadata.extend(extra.iter().map(|(key, value)| (key.clone(), value.clone())));
+        if let Some(message) = message {
+            snapshot.message = message.to_string();
+        }
+        let status = SaveStatus::Saved { id, message: snapshot.message.clone() };
+        history.save()?;
+        Ok(status)
+    }
+
+    /// Changes the message of snapshot `id`.
+    pub fn reword(&self, id: u32, message: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut history = self.history()?;
+        history.get_snapshot_mut(id)?.message = message.to_string();
+        history.save()
+    }
+
+    /// Writes the blobs of `manifest` that are not stored yet, then the manifest itself.
+    ///
+    /// Files are read again to store them, so a file edited since the walk hashed it
+    /// fails the save rather than storing content under the wrong hash.
+    fn store_manifest(&self, manifest: &Manifest, manifest_hash: &str, manifest_content: &[u8]) -> Result<()> {
+        let objects = self.objects();
+        for (path, entry) in manifest {
+            if !objects.contains(&entry.hash) {
+                let full_path = self.root.join(path);
+                let content = manifest::read_content(&full_path, &fs::symlink_metadata(&full_path)?)?;
+                if utils::hash_content(&content) != entry.hash {
+                    return Err(Error::ChangedDuringSave(path.clone()));
+                }
+                objects.write(&entry.hash, &content)?;
+            }
+        }
+        objects.write(manifest_hash, manifest_content)
+    }
+
+    /// Returns the walk options recorded with `snapshot`, falling back to the current
+    /// `.devcatrc` excludes for snapshots that predate recording them.
+    pub fn snapshot_walk_options(&self, snapshot: &Snapshot) -> Result<WalkOptions> {
+        let exclude = match &snapshot.excludes {
+            Some(excludes) => excludes.clone(),
+            None => config::load_config(&self.root)?.exclude,
+        };
+        Ok(WalkOptions {
+            patterns: Patterns { include: snapshot.walk.include.clone(), exclude },
+            hidden: snapshot.walk.hidden,
+            gitignore: !snapshot.walk.no_gitignore,
+        })
+    }
+
+    /// Builds a manifest of the working tree as it is on disk right now.
+    pub fn working_manifest(&self, options: &WalkOptions) -> Result<Manifest> {
+        if !self.use_index {
+            return utils::get_current_manifest(&self.root, options, self.jobs, None);
+        }
+        let mut index = Index::load(&self.history_dir());
+        let manifest = utils::get_current_manifest(&self.root, options, self.jobs, Some(&mut index))?;
+        index.save()?;
+        Ok(manifest)
+    }
+
+    /// Reads the manifest recorded by snapshot `id`.
+    pub fn manifest(&self, id: u32) -> Result<Manifest> {
+        let snapshot = self.snapshot(id)?;
+        self.read_manifest(&snapshot.manifest_hash)
+    }
+
+    pub fn read_manifest(&self, hash: &str) -> Result<Manifest> {
+        serde_json::from_slice(&self.read_object(hash)?).map_err(Into::into)
+    }
+
+    pub fn read_object(&self, hash: &str) -> Result<Vec<u8>> {
+        self.objects().read(hash)
+    }
+
+    /// Compares snapshot `from` against snapshot `to`, or against the working tree when `to` is `None`.
+    pub fn diff(&self, from: u32, to: Option<u32>, options: &WalkOptions) -> Result<Vec<FileChange>> {
+        let old_manifest = self.manifest(from)?;
+        let new_manifest = match to {
+            Some(id) => self.manifest(id)?,
+            None => self.working_manifest(options)?,
+        };
+        Ok(diff_manifests(&old_manifest, &new_manifest))
+    }
+
+    /// Verifies that every object referenced by the history exists and matches its hash.
+    pub fn fsck(&self) -> Result<FsckReport> {
+        // Verifying must not rewrite the store, so skip the upgrade `lock` would do.
+        let _lock = self.acquire_lock()?;
+        integrity::check(self)
+    }
+
+    /// Returns the hashes of every manifest and blob referenced by any snapshot.
+    ///
+    /// Fails if a manifest cannot be read, since its blobs would otherwise look unreachable.
+    pub fn reachable_objects(&self) -> Result<HashSet<String>> {
+        let mut reachable = HashSet::new();
+        for snapshot in self.history()?.snapshots {
+            reachable.extend(self.read_manifest(&snapshot.manifest_hash)?.into_values().map(|entry| entry.hash));
+            reachable.insert(snapshot.manifest_hash);
+        }
+        Ok(reachable)
+    }
+
+    /// Deletes every object not reachable from the history, and any temporary files left
+    /// by interrupted writes. With `dry_run`, only reports what would go.
+    pub fn gc(&self, dry_run: bool) -> Result<GcReport> {
+        // A dry run must not rewrite the store, so skip the upgrade `lock` would do.
+        let _lock = if dry_run { self.acquire_lock()? } else { self.lock()? };
+        let objects = self.objects();
+        let reachable = self.reachable_objects()?;
+
+        let mut report = GcReport::default();
+        for hash in objects.list()? {
+            if reachable.contains(&hash) {
+                continue;
+            }
+            report.reclaimed_bytes += if dry_run { objects.size(&hash)? } else { objects.remove(&hash)? };
+            report.removed.push(hash);
+        }
+        for path in objects.temp_files()? {
+            report.reclaimed_bytes += if dry_run { fs::metadata(&path)?.len() } else { objects.remove_file(&path)? };
+            report.temp_files.push(path);
+        }
+        Ok(report)
+    }
+
+    /// Returns the entries of snapshot `id` matching any of `pathspecs`.
+    ///
+    /// A pathspec is a file path, a directory (matching everything below it), or a glob,
+    /// all relative to the repository root. Every pathspec must match at least one entry.
+    pub fn select(&self, id: u32, pathspecs: &[String]) -> Result<Manifest> {
+        let manifest = self.manifest(id)?;
+        let mut selected = Manifest::new();
+        for pathspec in pathspecs {
+            let prefix: PathBuf = Path::new(pathspec)
+                .components()
+                .filter(|c| !matches!(c, std::path::Component::CurDir))
+                .collect();
+            let glob = Glob::new(&prefix.to_string_lossy())?.compile_matcher();
+            let matches: Vec<_> = manifest
+                .iter()
+                .filter(|(path, _)| path.starts_with(&prefix) || glob.is_match(path))
+                .collect();
+            if matches.is_empty() {
+                return Err(Error::PathspecNotMatched(pathspec.clone()));
+            }
+            selected.extend(matches.into_iter().map(|(path, entry)| (path.clone(), entry.clone())));
+        }
+        Ok(selected)
+    }
+
+    /// Writes the entries of snapshot `id` matching `pathspecs` back to disk, leaving every
+    /// other file alone. Files go under `dest` when given, otherwise under the repository root.
+    pub fn restore(&self, id: u32, pathspecs: &[String], dest: Option<&Path>) -> Result<Vec<PathBuf>> {
+        let selected = self.select(id, pathspecs)?;
+        let dest = dest.unwrap_or(&self.root);
+        for (path, entry) in &selected {
+            manifest::write_entry(&dest.join(path), entry, &self.read_object(&entry.hash)?)?;
+        }
+        Ok(selected.into_keys().collect())
+    }
+
+    /// Works out which files [`Repository::revert`] would create, overwrite, or delete.
+    ///
+    /// Only files that the snapshot's own include/exclude rules and ignore files would have
+    /// captured are considered, so untracked files are never deleted.
+    pub fn plan_revert(&self, id: u32) -> Result<RevertPlan> {
+        let snapshot = self.snapshot(id)?;
+        let target = self.read_manifest(&snapshot.manifest_hash)?;
+        let working = self.working_manifest(&self.snapshot_walk_options(&snapshot)?)?;
+        Ok(RevertPlan::new(&working, &target))
+    }
+
+    /// Rewrites the working tree to match snapshot `id` and makes it the current snapshot.
+    ///
+    /// The current state is saved as a pre-revert snapshot first, so the revert can be undone.
+    pub fn revert(&self, id: u32) -> Result<RevertOutcome> {
+        let _lock = self.lock()?;
+        let snapshot = self.snapshot(id)?;
+        let options = self.snapshot_walk_options(&snapshot)?;
+        let target = self.read_manifest(&snapshot.manifest_hash)?;
+        let plan = RevertPlan::new(&self.working_manifest(&options)?, &target);
+
+        if plan.is_empty() {
+            self.set_current(id)?;
+            return Ok(RevertOutcome { plan, backup: SaveStatus::NoChanges });
+        }
+        let backup = self.save_locked(&format!("Pre-revert snapshot before reverting to {}", id), &options, &Metadata::new())?;
+
+        for path in plan.create.iter().chain(&plan.modify) {
+            let entry = &target[path];
+            manifest::write_entry(&self.root.join(path), entry, &self.read_object(&entry.hash)?)?;
+        }
+        for path in &plan.delete {
+            fs::remove_file(self.root.join(path))?;
+        }
+        self.set_current(id)?;
+        Ok(RevertOutcome { plan, backup })
+    }
+
+    /// Records `id` as the snapshot the working tree is based on. Callers must hold the lock.
+    fn set_current(&self, id: u32) -> Result<()> {
+        let mut history = self.history()?;
+        history.set_current(id)?;
+        history.save()
+    }
+}
+
+/// Lists every path whose entry (content, kind or mode) differs between `old` and `new`, sorted by path.
+pub fn diff_manifests(old: &Manifest, new: &Manifest) -> Vec<FileChange> {
+    let all_paths: std::collections::BTreeSet<_> = old.keys().chain(new.keys()).collect();
+    all_paths
+        .into_iter()
+        .filter_map(|path| {
+            let old_entry = old.get(path);
+            let new_entry = new.get(path);
+            let unchanged = match (old_entry, new_entry) {
+                (Some(old_entry), Some(new_entry)) => old_entry.matches(new_entry),
+                _ => false,
+            };
+            (!unchanged).then(|| FileChange {
+                path: path.clone(),
+                old: old_entry.cloned(),
+                new: new_entry.cloned(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+use crate::{manifest, repository::Repository, Result};
+use clap::Parser;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(about = "Restores individual files or globs from a snapshot without touching anything else.")]
+pub struct RestoreArgs {
+    /// The snapshot to restore from: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`.
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub id: String,
+    /// Files, directories, or glob patterns to restore, relative to the current directory.
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+    /// Write the restored files here instead of their original location. A single restored
+    /// file is written to this exact path unless it is an existing directory.
+    #[arg(long, value_name = "PATH", conflicts_with = "stdout")]
+    pub to: Option<PathBuf>,
+    /// Print the restored file contents to stdout instead of writing files.
+    #[arg(long)]
+    pub stdout: bool,
+}
+
+pub fn run(args: RestoreArgs) -> Result<()> {
+    let repo = Repository::discover(Path::new("."))?;
+    let id = repo.resolve(&args.id)?;
+
+    let cwd = std::env::current_dir()?.canonicalize()?;
+    let prefix = cwd.strip_prefix(repo.root()).unwrap_or(Path::new(""));
+    let pathspecs: Vec<String> = args
+        .paths
+        .iter()
+        .map(|path| {
+            let path = Path::new(path);
+            let relative = match path.strip_prefix(repo.root()) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => normalize(&prefix.join(path)),
+            };
+            relative.to_string_lossy().into_owned()
+        })
+        .collect();
+
+    if args.stdout {
+        let mut stdout = io::stdout().lock();
+        for entry in repo.select(id, &pathspecs)?.values() {
+            stdout.write_all(&repo.read_object(&entry.hash)?)?;
+        }
+        return Ok(());
+    }
+
+    let restored = match &args.to {
+        Some(to) if !to.is_dir() => {
+            let selected = repo.select(id, &pathspecs)?;
+            if let [(path, entry)] = selected.iter().collect::<Vec<_>>()[..] {
+                manifest::write_entry(to, entry, &repo.read_object(&entry.hash)?)?;
+                println!("✅ Restored {} from snapshot {} to {}.", path.display(), id, to.display());
+                return Ok(());
+            }
+            repo.restore(id, &pathspecs, Some(to))?
+        }
+        Some(to) => repo.restore(id, &pathspecs, Some(to))?,
+        None => repo.restore(id, &pathspecs, None)?,
+    };
+
+    for path in &restored {
+        println!("- {}", path.display());
+    }
+    println!("✅ Restored {} files from snapshot {}.", restored.len(), id);
+    Ok(())
+}
+
+/// Resolves `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
@@ -1,54 +1,58 @@
-use crate::{history::History, Result};
+use crate::{repository::{Repository, RevertPlan}, utils::SaveStatus, Result};
 use clap::Parser;
-use ignore::WalkBuilder;
-use std::collections::{BTreeMap, HashSet};
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 #[derive(Parser, Debug)]
 #[command(about = "Reverts the working directory to a specific snapshot state.")]
 pub struct RevertArgs {
-    /// The snapshot ID to revert the working directory to.
-    #[arg(required = true)]
-    pub id: u32,
+    /// The snapshot to revert the working directory to: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`.
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub id: String,
+    /// List the files that would be written or deleted without touching them.
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 pub fn run(args: RevertArgs) -> Result<()> {
-    let root_path = Path::new(".");
-    let history = History::load(root_path)?;
-    let snapshot = history.get_snapshot(args.id)?;
+    let repo = Repository::discover(Path::new("."))?;
+    let id = repo.resolve(&args.id)?;
 
-    let objects_dir = root_path.join(".devcat").join("objects");
-    let manifest_content = fs::read(objects_dir.join(&snapshot.manifest_hash))?;
-    let manifest: BTreeMap<PathBuf, String> = serde_json::from_slice(&manifest_content)?;
-
-    for (path, hash) in &manifest {
-        let content = fs::read(objects_dir.join(hash))?;
-        if let Some(parent) = path.parent() {
-            if !parent.as_os_str().is_empty() {
-                fs::create_dir_all(parent)?;
-            }
+    if args.dry_run {
+        let plan = repo.plan_revert(id)?;
+        if plan.is_empty() {
+            println!("✅ Working directory already matches snapshot {}.", id);
+        } else {
+            print_plan(&plan);
+            println!("Dry run: no files were changed.");
         }
-        fs::write(path, content)?;
+        return Ok(());
     }
 
-    let manifest_paths: HashSet<_> = manifest.keys().collect();
-    let walker = WalkBuilder::new(root_path)
-        .filter_entry(|entry| !entry.path().starts_with("./.devcat"))
-        .build();
-
-    for result in walker {
-        let entry = result?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Ok(relative_path) = path.strip_prefix(root_path) {
-                if !manifest_paths.contains(&relative_path.to_path_buf()) {
-                    fs::remove_file(path)?;
-                }
-            }
-        }
+    let outcome = repo.revert(id)?;
+    if outcome.plan.is_empty() {
+        println!("✅ Working directory already matches snapshot {}.", id);
+        return Ok(());
     }
-    
-    println!("✅ Reverted working directory to snapshot {}.", args.id);
+    if let SaveStatus::Saved { id: backup_id, .. } = outcome.backup {
+        println!("💾 Saved pre-revert snapshot {}. Run `devcat revert {}` to undo.", backup_id, backup_id);
+    }
+    println!(
+        "✅ Reverted working directory to snapshot {} ({} written, {} deleted).",
+        id,
+        outcome.plan.create.len() + outcome.plan.modify.len(),
+        outcome.plan.delete.len()
+    );
     Ok(())
 }
+
+fn print_plan(plan: &RevertPlan) {
+    for path in &plan.create {
+        println!("+ {}", path.display());
+    }
+    for path in &plan.modify {
+        println!("~ {}", path.display());
+    }
+    for path in &plan.delete {
+        println!("- {}", path.display());
+    }
+}
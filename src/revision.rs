@@ -0,0 +1,125 @@
+//! Parsing of snapshot references ("revisions") accepted by every command.
+//!
+//! ```text
+//! 12            snapshot with ID 12
+//! latest        the newest snapshot
+//! current       the snapshot last saved or reverted to
+//! -1, -3        counted from the newest snapshot (-1 is the newest)
+//! green-tests   the snapshot a tag points at
+//! @{2h ago}     the newest snapshot at least two hours old
+//! @{2024-05-01 09:30:00}
+//!               the newest snapshot taken at or before that time (UTC)
+//! /regex        the newest snapshot whose message matches
+//! <rev>~N       the Nth ancestor of <rev>, following parent links (`~` alone means `~1`)
+//! ```
+
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::tags::Tags;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+/// The revision name that always refers to the newest snapshot.
+pub const LATEST: &str = "latest";
+
+/// The revision name for the snapshot the working tree was last saved as or reverted to.
+pub const CURRENT: &str = "current";
+
+/// Resolves `spec` to a snapshot ID using `history` and `tags`.
+pub fn resolve(spec: &str, history: &History, tags: &Tags) -> Result<u32> {
+    let spec = spec.trim();
+    let (base, back) = split_offset(spec)?;
+    let position = resolve_base(spec, base, history, tags)?;
+    let mut snapshot = &history.snapshots[position];
+    for _ in 0..back {
+        snapshot = history
+            .parent_of(snapshot)
+            .ok_or_else(|| invalid(spec, "goes back further than the oldest ancestor"))?;
+    }
+    Ok(snapshot.id)
+}
+
+/// Splits a trailing `~N` off `spec`. Message searches take the rest of the spec verbatim.
+fn split_offset(spec: &str) -> Result<(&str, usize)> {
+    if spec.starts_with('/') {
+        return Ok((spec, 0));
+    }
+    match spec.rsplit_once('~') {
+        Some((base, "")) => Ok((base, 1)),
+        Some((base, count)) => count
+            .parse()
+            .map(|count| (base, count))
+            .map_err(|_| invalid(spec, "`~` must be followed by a number")),
+        None => Ok((spec, 0)),
+    }
+}
+
+/// Returns the position in `history.snapshots` that `base` refers to.
+fn resolve_base(spec: &str, base: &str, history: &History, tags: &Tags) -> Result<usize> {
+    let snapshots = &history.snapshots;
+    let position_of = |id: u32| {
+        snapshots
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or(Error::SnapshotIdNotFound(id))
+    };
+
+    if base == LATEST {
+        return snapshots.len().checked_sub(1).ok_or(Error::NoSnapshots);
+    }
+    if base == CURRENT {
+        return position_of(history.get_current()?.id);
+    }
+    if let Some(count) = base.strip_prefix('-') {
+        let count: usize = count
+            .parse()
+            .map_err(|_| invalid(spec, "a negative offset must be a number"))?;
+        if snapshots.is_empty() {
+            return Err(Error::NoSnapshots);
+        }
+        return snapshots
+            .len()
+            .checked_sub(count)
+            .filter(|_| count > 0)
+            .ok_or_else(|| invalid(spec, "offset is out of range"));
+    }
+    if let Ok(id) = base.parse::<u32>() {
+        return position_of(id);
+    }
+    if let Some(time) = base.strip_prefix("@{").and_then(|rest| rest.strip_suffix('}')) {
+        let cutoff = parse_time(spec, time)?;
+        return snapshots
+            .iter()
+            .rposition(|s| s.timestamp <= cutoff)
+            .ok_or_else(|| Error::UnknownSnapshot(spec.to_string()));
+    }
+    if let Some(pattern) = base.strip_prefix('/') {
+        let re = Regex::new(pattern)?;
+        return snapshots
+            .iter()
+            .rposition(|s| re.is_match(&s.message))
+            .ok_or_else(|| Error::UnknownSnapshot(spec.to_string()));
+    }
+    match tags.get(base) {
+        Some(id) => position_of(id),
+        None => Err(Error::UnknownSnapshot(spec.to_string())),
+    }
+}
+
+/// Parses either a relative time (`2h ago`, `30min`) or an absolute UTC timestamp.
+fn parse_time(spec: &str, time: &str) -> Result<DateTime<Utc>> {
+    let time = time.trim();
+    let relative = time.strip_suffix("ago").unwrap_or(time).trim();
+    if let Ok(duration) = humantime::parse_duration(relative) {
+        let duration = chrono::Duration::from_std(duration)
+            .map_err(|_| invalid(spec, "duration is too large"))?;
+        return Ok(Utc::now() - duration);
+    }
+    humantime::parse_rfc3339_weak(time)
+        .map(DateTime::<Utc>::from)
+        .map_err(|_| invalid(spec, "expected a duration like `2h ago` or a timestamp like `2024-05-01 09:30:00`"))
+}
+
+fn invalid(spec: &str, reason: &str) -> Error {
+    Error::InvalidRevision(spec.to_string(), reason.to_string())
+}
@@ -0,0 +1,22 @@
+use crate::{repository::Repository, Result};
+use clap::Parser;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[command(about = "Changes the message of an existing snapshot.")]
+pub struct RewordArgs {
+    /// The snapshot to reword: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`.
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub id: String,
+    /// The new message.
+    #[arg(required = true)]
+    pub message: String,
+}
+
+pub fn run(args: RewordArgs) -> Result<()> {
+    let repo = Repository::discover(Path::new("."))?;
+    let id = repo.resolve(&args.id)?;
+    repo.reword(id, &args.message)?;
+    println!("✅ Snapshot {} reworded: {}", id, args.message);
+    Ok(())
+}
@@ -1,25 +1,49 @@
-use crate::{config, utils, Result, ExcludeArgs};
+use crate::{config, metadata::Metadata, repository::Repository, utils, Result, WalkArgs, CacheArgs, JobsArgs};
 use clap::Parser;
 use std::path::Path;
 
 #[derive(Parser, Debug)]
 #[command(about = "Saves a new versioned snapshot of the current code state.")]
 pub struct SaveArgs {
-    /// A short message describing the snapshot.
-    #[arg(required = true)]
-    pub message: String,
+    /// A short message describing the snapshot. Optional with --amend.
+    #[arg(required_unless_present = "amend")]
+    pub message: Option<String>,
+    /// Replace the current snapshot with the working tree instead of adding a new one.
+    #[arg(long)]
+    pub amend: bool,
+    /// Extra metadata to record with the snapshot, e.g. `--meta model=gpt-4o`. Repeatable.
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = crate::parse_meta)]
+    pub meta: Vec<(String, String)>,
     #[command(flatten)]
-    pub exclude_args: ExcludeArgs,
+    pub walk_args: WalkArgs,
+    #[command(flatten)]
+    pub cache_args: CacheArgs,
+    #[command(flatten)]
+    pub jobs_args: JobsArgs,
 }
 
 pub fn run(args: SaveArgs) -> Result<()> {
-    let root_path = Path::new(".");
-    let config = config::load_config(root_path)?;
+    let mut repo = Repository::discover(Path::new("."))?;
+    repo.set_use_index(!args.cache_args.no_cache);
+    repo.set_jobs(args.jobs_args.jobs);
+    let config = config::load_config(repo.root())?;
     
-    let mut excludes = args.exclude_args.exclude;
-    excludes.extend(config.exclude);
+    let options = args.walk_args.options(&config);
     
-    match utils::perform_save(root_path, &args.message, &excludes)? {
+    let extra: Metadata = args.meta.into_iter().collect();
+    if args.amend {
+        match repo.amend(args.message.as_deref(), &options, &extra)? {
+            utils::SaveStatus::Saved { id, message } => {
+                println!("✅ Snapshot {} amended: {}", id, message);
+            }
+            utils::SaveStatus::NoChanges => {
+                println!("✅ Nothing to amend: the working tree and message match the current snapshot.");
+            }
+        }
+        return Ok(());
+    }
+
+    match repo.save(args.message.as_deref().unwrap_or_default(), &options, &extra)? {
         utils::SaveStatus::Saved { id, message } => {
             println!("✅ Snapshot {} saved: {}", id, message);
         }
@@ -0,0 +1,181 @@
+use crate::error::{Error, Result};
+use crate::{object, utils};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Content-addressed object storage under `.devcat/objects`.
+///
+/// Objects live in fan-out subdirectories keyed by the first two hex digits of
+/// their hash (`objects/ab/cdef…`). Objects in the older flat layout are still
+/// readable until the store is upgraded (see [`crate::format`]).
+#[derive(Debug, Clone)]
+pub struct ObjectStore {
+    dir: PathBuf,
+}
+
+impl ObjectStore {
+    pub fn new(dir: PathBuf) -> Self {
+        ObjectStore { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns where `hash` is stored in the fan-out layout.
+    pub fn path_for(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(hash.len().min(2));
+        self.dir.join(prefix).join(rest)
+    }
+
+    fn legacy_path_for(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash)
+    }
+
+    /// Locates an existing object in either layout.
+    fn find(&self, hash: &str) -> Option<PathBuf> {
+        [self.path_for(hash), self.legacy_path_for(hash)]
+            .into_iter()
+            .find(|path| path.is_file())
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.find(hash).is_some()
+    }
+
+    /// Reads and decodes the object stored under `hash`.
+    pub fn read(&self, hash: &str) -> Result<Vec<u8>> {
+        let path = self.find(hash).ok_or_else(|| Error::ObjectNotFound(hash.to_string()))?;
+        object::decode(hash, fs::read(path)?)
+    }
+
+    /// Encodes and stores `content` under `hash`, unless an object with that hash already exists.
+    pub fn write(&self, hash: &str, content: &[u8]) -> Result<()> {
+        if self.contains(hash) {
+            return Ok(());
+        }
+        let path = self.path_for(hash);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        utils::write_atomic(&path, &object::encode(content)?)
+    }
+
+    /// Returns the on-disk size of the object stored under `hash`.
+    pub fn size(&self, hash: &str) -> Result<u64> {
+        let path = self.find(hash).ok_or_else(|| Error::ObjectNotFound(hash.to_string()))?;
+        Ok(fs::metadata(path)?.len())
+    }
+
+    /// Deletes the object stored under `hash` and returns the number of bytes freed.
+    pub fn remove(&self, hash: &str) -> Result<u64> {
+        let Some(path) = self.find(hash) else {
+            return Ok(0);
+        };
+        self.remove_file(&path)
+    }
+
+    /// Deletes a file inside the store, such as one from [`ObjectStore::temp_files`],
+    /// and returns the number of bytes freed.
+    pub fn remove_file(&self, path: &Path) -> Result<u64> {
+        let size = fs::metadata(path)?.len();
+        fs::remove_file(path)?;
+        if let Some(shard) = path.parent()
+            && shard != self.dir
+        {
+            // Only succeeds once the shard directory is empty.
+            let _ = fs::remove_dir(shard);
+        }
+        Ok(size)
+    }
+
+    /// Lists the hashes of every stored object, in both layouts.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut hashes = Vec::new();
+        if !self.dir.exists() {
+            return Ok(hashes);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if is_temp_file(&name) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                for object in fs::read_dir(entry.path())? {
+                    let object_name = object?.file_name().to_string_lossy().into_owned();
+                    if !is_temp_file(&object_name) {
+                        hashes.push(format!("{}{}", name, object_name));
+                    }
+                }
+            } else {
+                hashes.push(name);
+            }
+        }
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// Lists temporary files left behind by interrupted writes, in both layouts.
+    ///
+    /// Objects are only written while holding the repository lock, so with the lock held
+    /// every temporary file is an orphan.
+    pub fn temp_files(&self) -> Result<Vec<PathBuf>> {
+        let mut temp_files = Vec::new();
+        if !self.dir.exists() {
+            return Ok(temp_files);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() && !is_temp_file(&name) {
+                for object in fs::read_dir(entry.path())? {
+                    let object = object?;
+                    if is_temp_file(&object.file_name().to_string_lossy()) {
+                        temp_files.push(object.path());
+                    }
+                }
+            } else if is_temp_file(&name) {
+                temp_files.push(entry.path());
+            }
+        }
+        temp_files.sort();
+        Ok(temp_files)
+    }
+
+    /// Moves objects from the flat layout into fan-out subdirectories.
+    ///
+    /// Returns the number of objects moved.
+    pub fn migrate(&self) -> Result<usize> {
+        let mut moved = 0;
+        if !self.dir.exists() {
+            return Ok(moved);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let hash = entry.file_name().to_string_lossy().into_owned();
+            if !entry.file_type()?.is_file() || is_temp_file(&hash) {
+                continue;
+            }
+            let target = self.path_for(&hash);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if target.exists() {
+                debug!("Object {} already migrated, removing flat copy", hash);
+                fs::remove_file(entry.path())?;
+            } else {
+                debug!("Migrating object {}", hash);
+                fs::rename(entry.path(), target)?;
+            }
+            moved += 1;
+        }
+        Ok(moved)
+    }
+}
+
+/// Leftovers from an interrupted [`utils::write_atomic`] are hidden dot-files.
+fn is_temp_file(name: &str) -> bool {
+    name.starts_with('.')
+}
@@ -0,0 +1,58 @@
+use crate::{output, repository::Repository, revision, OutputArgs, Result};
+use clap::Parser;
+use std::fmt::Write;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[command(about = "Creates, lists, or deletes named tags for snapshots.")]
+pub struct TagArgs {
+    /// The tag name. Lists all tags when omitted.
+    pub name: Option<String>,
+    /// The snapshot to tag: ID, tag, `latest~N`, `-N`, `@{2h ago}` or `/regex`. Defaults to the current snapshot.
+    #[arg(allow_hyphen_values = true)]
+    pub snapshot: Option<String>,
+    /// Delete the named tag instead of creating it.
+    #[arg(short, long, requires = "name", conflicts_with = "snapshot")]
+    pub delete: bool,
+    /// Move the tag if it already exists.
+    #[arg(short, long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub output_args: OutputArgs,
+}
+
+pub fn run(args: TagArgs) -> Result<()> {
+    let repo = Repository::discover(Path::new("."))?;
+
+    let Some(name) = args.name else {
+        return list(&repo, &args.output_args);
+    };
+
+    if args.delete {
+        let id = repo.delete_tag(&name)?;
+        println!("✅ Deleted tag {} (was snapshot {}).", name, id);
+        return Ok(());
+    }
+
+    let id = repo.resolve(args.snapshot.as_deref().unwrap_or(revision::CURRENT))?;
+    repo.tag(&name, id, args.force)?;
+    println!("✅ Tagged snapshot {} as {}.", id, name);
+    Ok(())
+}
+
+fn list(repo: &Repository, output_args: &OutputArgs) -> Result<()> {
+    let tags = repo.tags()?;
+    let mut output = String::new();
+
+    if tags.tags.is_empty() {
+        writeln!(&mut output, "No tags found. Run `devcat tag <name> [snapshot]` to create one.")?;
+    } else {
+        let width = tags.tags.keys().map(|name| name.len()).max().unwrap_or(0).max(3);
+        writeln!(&mut output, "{:<width$} ID", "TAG")?;
+        for (name, id) in &tags.tags {
+            writeln!(&mut output, "{:<width$} {}", name, id)?;
+        }
+    }
+
+    output::handle_output(output, output_args, "Tag list")
+}
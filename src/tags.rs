@@ -0,0 +1,69 @@
+use crate::error::{Error, Result};
+use crate::{revision, utils};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Human-readable names attached to snapshot IDs, stored in `.devcat/tags`.
+#[derive(Debug, Default, Clone)]
+pub struct Tags {
+    pub tags: BTreeMap<String, u32>,
+    path: PathBuf,
+}
+
+impl Tags {
+    pub fn load(root_path: &Path) -> Result<Self> {
+        let path = root_path.join(utils::HISTORY_DIR).join("tags");
+        let tags = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Tags { tags, path })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        utils::write_atomic(&self.path, &serde_json::to_vec_pretty(&self.tags)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.tags.get(name).copied()
+    }
+
+    /// Points `name` at snapshot `id`. An existing tag is only moved when `force` is set.
+    pub fn set(&mut self, name: &str, id: u32, force: bool) -> Result<()> {
+        validate_name(name)?;
+        if !force && self.tags.contains_key(name) {
+            return Err(Error::TagExists(name.to_string()));
+        }
+        self.tags.insert(name.to_string(), id);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<u32> {
+        self.tags.remove(name).ok_or_else(|| Error::TagNotFound(name.to_string()))
+    }
+
+    /// Returns the names pointing at snapshot `id`, sorted.
+    pub fn names_for(&self, id: u32) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|(_, tagged)| **tagged == id)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Tag names start with a letter and contain only letters, digits, `.`, `_` and `-`,
+/// so they can never be mistaken for a snapshot ID or another revision expression.
+fn validate_name(name: &str) -> Result<()> {
+    let valid = name != revision::LATEST
+        && name != revision::CURRENT
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidTagName(name.to_string()))
+    }
+}
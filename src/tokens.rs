@@ -0,0 +1,165 @@
+//! Token estimates and `--max-tokens` budgets for assembled context.
+//!
+//! Counts are estimated from the character count with a chars-per-token ratio
+//! rather than a model-specific tokenizer. Four characters per token is close
+//! to what BPE tokenizers produce for English text and source code.
+
+use crate::error::Result;
+use crate::render::{Body, ContextFile, Formatter};
+use std::path::PathBuf;
+
+pub const DEFAULT_CHARS_PER_TOKEN: f64 = 4.0;
+
+/// Truncation is only attempted while at least this many tokens of budget remain.
+const MIN_TRUNCATED_TOKENS: usize = 64;
+
+const TRUNCATION_NOTE: &str = "[... truncated to fit --max-tokens ...]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenEstimator {
+    chars_per_token: f64,
+}
+
+impl Default for TokenEstimator {
+    fn default() -> Self {
+        TokenEstimator { chars_per_token: DEFAULT_CHARS_PER_TOKEN }
+    }
+}
+
+impl TokenEstimator {
+    /// Uses `chars_per_token`, falling back to the default for non-positive ratios.
+    pub fn new(chars_per_token: f64) -> Self {
+        if chars_per_token > 0.0 {
+            TokenEstimator { chars_per_token }
+        } else {
+            TokenEstimator::default()
+        }
+    }
+
+    pub fn estimate(&self, text: &str) -> usize {
+        (text.chars().count() as f64 / self.chars_per_token).ceil() as usize
+    }
+
+    /// Estimates `file` as `formatter` would render it, framing included.
+    pub fn estimate_file(&self, file: &ContextFile, formatter: &dyn Formatter) -> Result<usize> {
+        let mut rendered = String::new();
+        formatter.file(file, &mut rendered)?;
+        Ok(self.estimate(&rendered))
+    }
+
+    fn chars_for(&self, tokens: usize) -> usize {
+        (tokens as f64 * self.chars_per_token) as usize
+    }
+}
+
+/// Token estimate for one file of the assembled context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTokens {
+    pub path: PathBuf,
+    pub tokens: usize,
+}
+
+/// What [`fit`] kept, cut short, and left out.
+#[derive(Debug, Default, Clone)]
+pub struct Budgeted {
+    /// The files to emit, in their original order.
+    pub files: Vec<ContextFile>,
+    /// Estimates for the emitted files, after truncation.
+    pub included: Vec<FileTokens>,
+    /// Files that were cut short, with their estimate before truncation.
+    pub truncated: Vec<FileTokens>,
+    /// Files left out entirely, with their estimate.
+    pub dropped: Vec<FileTokens>,
+}
+
+impl Budgeted {
+    pub fn total_tokens(&self) -> usize {
+        self.included.iter().map(|file| file.tokens).sum()
+    }
+}
+
+/// Keeps as much of `files` as fits in `max_tokens` (everything when `None`).
+///
+/// Smaller files have priority, so a single large file cannot crowd out the rest:
+/// files are admitted smallest first, the first one that does not fit is truncated
+/// if a useful amount of budget is left, and everything larger is dropped.
+pub fn fit(
+    files: Vec<ContextFile>,
+    max_tokens: Option<usize>,
+    estimator: &TokenEstimator,
+    formatter: &dyn Formatter,
+) -> Result<Budgeted> {
+    let estimates = files
+        .iter()
+        .map(|file| estimator.estimate_file(file, formatter))
+        .collect::<Result<Vec<_>>>()?;
+
+    let Some(max_tokens) = max_tokens else {
+        let included = files.iter().zip(&estimates).map(|(file, &tokens)| file_tokens(file, tokens)).collect();
+        return Ok(Budgeted { files, included, ..Budgeted::default() });
+    };
+
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by_key(|&i| (estimates[i], i));
+
+    let mut remaining = max_tokens;
+    let mut keep: Vec<Option<ContextFile>> = vec![None; files.len()];
+    let mut outcome = Budgeted::default();
+    for i in order {
+        let file = &files[i];
+        if estimates[i] <= remaining {
+            remaining -= estimates[i];
+            keep[i] = Some(file.clone());
+            continue;
+        }
+        if remaining >= MIN_TRUNCATED_TOKENS
+            && let Some(truncated) = truncate(file, remaining, estimator, formatter)?
+        {
+            remaining = remaining.saturating_sub(estimator.estimate_file(&truncated, formatter)?);
+            outcome.truncated.push(file_tokens(file, estimates[i]));
+            keep[i] = Some(truncated);
+            continue;
+        }
+        outcome.dropped.push(file_tokens(file, estimates[i]));
+    }
+
+    for file in keep.into_iter().flatten() {
+        outcome.included.push(file_tokens(&file, estimator.estimate_file(&file, formatter)?));
+        outcome.files.push(file);
+    }
+    outcome.truncated.sort_by(|a, b| a.path.cmp(&b.path));
+    outcome.dropped.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(outcome)
+}
+
+/// Cuts `file` down to at most `budget` tokens, ending on a line boundary.
+/// Returns `None` if not even the framing fits.
+fn truncate(
+    file: &ContextFile,
+    budget: usize,
+    estimator: &TokenEstimator,
+    formatter: &dyn Formatter,
+) -> Result<Option<ContextFile>> {
+    let Body::Content(content) = &file.body else {
+        return Ok(None);
+    };
+    let frame = ContextFile { body: Body::Content(TRUNCATION_NOTE.to_string()), ..file.clone() };
+    let overhead = estimator.estimate_file(&frame, formatter)?;
+    let Some(available) = budget.checked_sub(overhead + 1) else {
+        return Ok(None);
+    };
+
+    let mut end = content
+        .char_indices()
+        .nth(estimator.chars_for(available))
+        .map_or(content.len(), |(index, _)| index);
+    if let Some(newline) = content[..end].rfind('\n') {
+        end = newline + 1;
+    }
+    let body = Body::Content(format!("{}{}", &content[..end], TRUNCATION_NOTE));
+    Ok(Some(ContextFile { body, ..file.clone() }))
+}
+
+fn file_tokens(file: &ContextFile, tokens: usize) -> FileTokens {
+    FileTokens { path: file.path.clone(), tokens }
+}
@@ -1,4 +1,6 @@
-use crate::{utils, OutputArgs, Result};
+use crate::render::{Body, ContextFile, Document};
+use crate::tokens::TokenEstimator;
+use crate::{output, tree, FormatArgs, OutputArgs, Result};
 use clap::Parser;
 use log::debug;
 use regex::Regex;
@@ -15,6 +17,8 @@ pub struct TraceArgs {
     #[arg(short, long, default_value_t = 5)]
     context: usize,
     #[command(flatten)]
+    format_args: FormatArgs,
+    #[command(flatten)]
     output_args: OutputArgs,
 }
 
@@ -23,7 +27,7 @@ pub fn run(args: TraceArgs) -> Result<()> {
     let mut buffer = String::new();
     io::stdin().read_to_string(&mut buffer)?;
 
-    let mut output = String::new();
+    let mut files = Vec::new();
     let re = Regex::new(r"([a-zA-Z0-9_/\.\-]+):(\d+)")?;
     let mut seen = HashSet::new();
 
@@ -37,28 +41,36 @@ pub fn run(args: TraceArgs) -> Result<()> {
                 if !file_path.exists() || !seen.insert(file_path.to_path_buf()) { continue; }
 
                 debug!("Found reference to {} at line {}", file_path.display(), line_num);
-                writeln!(&mut output, "--- START FILE: {} (line {}) ---", file_path.display(), line_num)?;
-                match fs::read_to_string(file_path) {
+                let body = match fs::read_to_string(file_path) {
                     Ok(content) => {
                         let lines: Vec<&str> = content.lines().collect();
                         let start = (line_num - 1).saturating_sub(args.context);
                         let end = (line_num).saturating_add(args.context).min(lines.len());
 
+                        let mut excerpt = String::new();
                         for i in start..end {
                             let current_line_num = i + 1;
                             let prefix = if current_line_num == line_num { ">>" } else { "  " };
                             if let Some(line_content) = lines.get(i) {
-                                writeln!(&mut output, "{:>4} {} {}", current_line_num, prefix, line_content)?;
+                                writeln!(&mut excerpt, "{:>4} {} {}", current_line_num, prefix, line_content)?;
                             }
                         }
+                        Body::Content(excerpt.strip_suffix('\n').unwrap_or(&excerpt).to_string())
                     }
-                    Err(_) => {
-                        writeln!(&mut output, "[Could not read file]")?;
-                    }
-                }
-                writeln!(&mut output, "--- END FILE: {} ---\n", file_path.display())?;
+                    Err(_) => Body::Skipped("Could not read file".to_string()),
+                };
+                files.push(ContextFile { path: file_path.to_path_buf(), line: Some(line_num), body });
             }
         }
     }
-    utils::handle_output(output, &args.output_args, "Trace context")
+    let formatter = args.format_args.format.formatter();
+    let tree = if args.format_args.tree {
+        let estimator = TokenEstimator::default();
+        let estimates = files.iter().map(|file| estimator.estimate_file(file, formatter)).collect::<Result<Vec<_>>>()?;
+        Some(tree::render(&files, &estimates))
+    } else {
+        None
+    };
+    let document = Document { files: &files, tree: tree.as_deref(), ..Document::default() };
+    output::write_document(formatter, document, &args.output_args, "Trace context")
 }
@@ -0,0 +1,81 @@
+//! The `--tree` overview of the files in a context document.
+
+use crate::render::{Body, ContextFile};
+use crate::utils;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Component;
+
+/// Size and token estimate of one file or directory in the tree.
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    files: usize,
+    /// Bytes of included content; files listed without content count as zero.
+    bytes: u64,
+    tokens: usize,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    totals: Totals,
+    /// Set for files: whether the content was left out.
+    skipped: bool,
+    children: BTreeMap<String, Node>,
+}
+
+/// Draws `files` as a directory tree, annotating every entry with its size and
+/// estimated tokens. `tokens` holds the estimate for each file, in the same order.
+pub fn render(files: &[ContextFile], tokens: &[usize]) -> String {
+    let mut root = Node::default();
+    for (file, &file_tokens) in files.iter().zip(tokens) {
+        let (bytes, skipped) = match &file.body {
+            Body::Content(content) => (content.len() as u64, false),
+            Body::Skipped(_) => (0, true),
+        };
+        let file_totals = Totals { files: 1, bytes, tokens: file_tokens };
+        let mut node = &mut root;
+        add(&mut node.totals, file_totals);
+        // Absolute paths (from `trace`) hang off the same root as relative ones.
+        for component in file.path.components().filter(|c| matches!(c, Component::Normal(_))) {
+            node = node.children.entry(component.as_os_str().to_string_lossy().into_owned()).or_default();
+            add(&mut node.totals, file_totals);
+        }
+        node.skipped = skipped;
+    }
+
+    let mut output = String::new();
+    let _ = writeln!(output, ". {}", describe(&root, true));
+    draw(&root, "", &mut output);
+    output
+}
+
+fn add(totals: &mut Totals, file: Totals) {
+    totals.files += file.files;
+    totals.bytes += file.bytes;
+    totals.tokens += file.tokens;
+}
+
+fn draw(node: &Node, prefix: &str, output: &mut String) {
+    let count = node.children.len();
+    for (index, (name, child)) in node.children.iter().enumerate() {
+        let last = index + 1 == count;
+        let branch = if last { "└── " } else { "├── " };
+        let is_dir = !child.children.is_empty();
+        let slash = if is_dir { "/" } else { "" };
+        let _ = writeln!(output, "{}{}{}{} {}", prefix, branch, name, slash, describe(child, is_dir));
+        draw(child, &format!("{}{}", prefix, if last { "    " } else { "│   " }), output);
+    }
+}
+
+/// `(3 files, 4.2 KiB, ~1100 tokens)` for directories, `(1.0 KiB, ~260 tokens)` for files.
+fn describe(node: &Node, is_dir: bool) -> String {
+    let size = utils::format_bytes(node.totals.bytes);
+    if is_dir {
+        let files = if node.totals.files == 1 { "file" } else { "files" };
+        format!("({} {}, {}, ~{} tokens)", node.totals.files, files, size, node.totals.tokens)
+    } else if node.skipped {
+        format!("(~{} tokens, content skipped)", node.totals.tokens)
+    } else {
+        format!("({}, ~{} tokens)", size, node.totals.tokens)
+    }
+}
@@ -1,193 +1,150 @@
-use crate::{error::{Error, Result}, history::History, OutputArgs};
-use globset::{Glob, GlobSetBuilder};
-use ignore::WalkBuilder;
-use log::debug;
+use crate::manifest::{self, Entry, Manifest};
+use crate::walk::{self, WalkOptions};
+use crate::{error::{Error, Result}, index::Index};
+use log::warn;
 use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use std::fs;
-use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::io::{Read, Write};
+use std::path::Path;
 
 pub const FILE_SIGNATURE: &str = "// DEVCAT-OUTPUT-FILE";
+/// The value of the `"devcat"` field that marks JSON output, which cannot hold a comment.
+pub const OUTPUT_MARKER: &str = "DEVCAT-OUTPUT-FILE";
+/// How signed JSON output starts; see [`crate::render::Document`].
+pub const JSON_SIGNATURE: &str = "{\"devcat\": \"DEVCAT-OUTPUT-FILE\"";
 pub const HISTORY_DIR: &str = ".devcat";
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SaveStatus {
     Saved { id: u32, message: String },
     NoChanges,
 }
 
+/// Formats a byte count with a binary unit suffix, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Parses a size such as `4096`, `512K`, `10M` or `1G` (binary units, case-insensitive,
+/// optional trailing `B` or `iB`) into bytes.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let invalid = || Error::InvalidSize(text.to_string());
+    let upper = text.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, shift) = match number.char_indices().last() {
+        Some((index, 'K')) => (&number[..index], 10),
+        Some((index, 'M')) => (&number[..index], 20),
+        Some((index, 'G')) => (&number[..index], 30),
+        _ => (number, 0),
+    };
+    let value: u64 = digits.trim().parse().map_err(|_| invalid())?;
+    value.checked_mul(1 << shift).ok_or_else(invalid)
+}
+
 pub fn hash_content(content: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content);
     format!("{:x}", hasher.finalize())
 }
 
-pub fn handle_output(content: String, output_args: &OutputArgs, context_name: &str) -> Result<()> {
-    if let Some(path) = &output_args.output {
-        debug!("Writing output to file: {}", path.display());
-        let final_content = format!("{}\n{}", FILE_SIGNATURE, content);
-        fs::write(path, final_content)?;
-        println!("✅ {} context saved to {}", context_name, path.display());
-    } else {
-        print!("{}", content);
+/// Replaces `path` with `content` so that readers see either the old or the new file, never a partial one.
+///
+/// The content is written to a temporary sibling, flushed to disk, and renamed over `path`.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
     }
+    result?;
+
+    #[cfg(unix)]
+    fs::File::open(dir)?.sync_all()?;
     Ok(())
 }
 
+/// Whether `path` was written by devcat: it starts with [`FILE_SIGNATURE`], or with
+/// [`JSON_SIGNATURE`] for JSON output.
 pub fn check_file_signature(path: &Path) -> Result<bool> {
-    let mut file = match fs::File::open(path) {
+    let file = match fs::File::open(path) {
         Ok(f) => f,
         Err(_) => return Ok(false),
     };
-    let signature_bytes = FILE_SIGNATURE.as_bytes();
-    let mut buffer = vec![0; signature_bytes.len()];
-    match Read::read_exact(&mut file, &mut buffer) {
-        Ok(_) => Ok(buffer == signature_bytes),
-        Err(_) => Ok(false),
+    let mut buffer = Vec::new();
+    let limit = FILE_SIGNATURE.len().max(JSON_SIGNATURE.len()) as u64;
+    if file.take(limit).read_to_end(&mut buffer).is_err() {
+        return Ok(false);
     }
+    Ok(buffer.starts_with(FILE_SIGNATURE.as_bytes()) || buffer.starts_with(JSON_SIGNATURE.as_bytes()))
 }
 
-pub fn append_file_content(
-    full_path: &Path,
-    relative_path: &Path,
-    output: &mut String,
-) -> Result<()> {
-    use std::fmt::Write;
-    writeln!(output, "--- START FILE: {} ---", relative_path.display())?;
-    match fs::read_to_string(full_path) {
-        Ok(content) => writeln!(output, "{}", content)?,
-        Err(_) => writeln!(output, "[Skipped binary file]")?,
-    }
-    writeln!(output, "--- END FILE: {} ---\n", relative_path.display())?;
-    Ok(())
-}
-
-pub fn ensure_history_dir_exists() -> Result<PathBuf> {
-    let path = PathBuf::from(HISTORY_DIR);
-    if !path.exists() {
-        debug!("Creating history directory at {}", path.display());
-        fs::create_dir_all(&path)?;
-    }
-    Ok(path)
-}
-
+/// Hashes every file under `root_path` selected by `options` using `jobs` threads (0 picks automatically).
+///
+/// When an `index` is given, files whose stat info is unchanged reuse their cached hash.
+/// Paths that cannot be read are left out of the manifest and logged as warnings.
 pub fn get_current_manifest(
     root_path: &Path,
-    excludes: &[String],
-) -> Result<BTreeMap<PathBuf, String>> {
-    let mut manifest = BTreeMap::new();
-    let mut skipped_items = Vec::new();
-
-    let mut glob_builder = GlobSetBuilder::new();
-    for pattern in excludes {
-        let glob = Glob::new(&format!("**/{}", pattern))?;
-        glob_builder.add(glob);
-    }
-    let exclude_set = glob_builder.build()?;
-
-    let walker = WalkBuilder::new(root_path)
-        .follow_links(false)
-        .filter_entry(move |entry| {
-            if entry.path().starts_with("./.devcat") {
-                return false;
-            }
-            if exclude_set.is_match(entry.path()) {
-                debug!("Excluding path via --exclude: {}", entry.path().display());
-                return false;
-            }
-            true
-        })
-        .build();
-
-    for result in walker {
-        let entry = match result {
-            Ok(entry) => entry,
-            Err(e) => {
-                let path_str = match &e {
-                    ignore::Error::WithPath { path, .. } => path.to_string_lossy().to_string(),
-                    _ => "[Unknown Path]".to_string(),
-                };
-                skipped_items.push((path_str, e.to_string()));
-                continue;
-            }
+    options: &WalkOptions,
+    jobs: usize,
+    index: Option<&mut Index>,
+) -> Result<Manifest> {
+    let cached = index.as_deref();
+    let results = walk::walk(root_path, options, jobs, |path, metadata| {
+        let relative_path = match path.strip_prefix(root_path) {
+            Ok(relative_path) if !relative_path.as_os_str().is_empty() => relative_path,
+            _ => return Ok(None),
         };
 
-        let path = entry.path();
-        if path.is_file() {
-            if check_file_signature(path)? {
-                debug!("Skipping devcat output file: {}", path.display());
-                continue;
-            }
-            let content = match fs::read(path) {
-                Ok(content) => content,
-                Err(e) => {
-                    skipped_items.push((path.to_string_lossy().to_string(), e.to_string()));
-                    continue;
-                }
-            };
-            let hash = hash_content(&content);
-            if let Ok(relative_path) = path.strip_prefix(root_path) {
-                if !relative_path.as_os_str().is_empty() {
-                    manifest.insert(relative_path.to_path_buf(), hash);
-                }
-            }
+        if let Some(index) = cached
+            && let Some(hash) = index.lookup(relative_path, metadata)
+        {
+            return Ok(Some((Entry::from_metadata(metadata, hash.to_string()), None)));
         }
-    }
 
-    if !skipped_items.is_empty() {
-        eprintln!("\n⚠️ The following paths were skipped due to errors:");
-        for (path, error) in skipped_items {
-            eprintln!("- {}: {}", path, error);
-        }
-    }
+        let hash = hash_content(&manifest::read_content(path, metadata)?);
+        Ok(Some((Entry::from_metadata(metadata, hash), Some(metadata.clone()))))
+    })?;
 
-    Ok(manifest)
-}
-
-pub fn get_manifest_from_hash(root_path: &Path, hash: &str) -> Result<BTreeMap<PathBuf, String>> {
-    let objects_dir = root_path.join(HISTORY_DIR).join("objects");
-    let manifest_path = objects_dir.join(hash);
-    if !manifest_path.exists() {
-        return Err(Error::ObjectNotFound(hash.to_string()));
-    }
-    let manifest_content = fs::read(manifest_path)?;
-    serde_json::from_slice(&manifest_content).map_err(Into::into)
-}
-
-pub fn perform_save(root_path: &Path, message: &str, excludes: &[String]) -> Result<SaveStatus> {
-    ensure_history_dir_exists()?;
-    
-    let objects_dir = root_path.join(HISTORY_DIR).join("objects");
-    fs::create_dir_all(&objects_dir)?;
-
-    let mut history = History::load(root_path)?;
-    let manifest = get_current_manifest(root_path, excludes)?;
-    let manifest_content = serde_json::to_vec(&manifest)?;
-    let current_manifest_hash = hash_content(&manifest_content);
-
-    if let Ok(latest) = history.get_latest() {
-        if latest.manifest_hash == current_manifest_hash {
-            return Ok(SaveStatus::NoChanges);
+    let mut manifest = BTreeMap::new();
+    let mut index = index;
+    for (path, (entry, fresh_metadata)) in results.files {
+        let Ok(relative_path) = path.strip_prefix(root_path) else {
+            continue;
+        };
+        if let (Some(index), Some(metadata)) = (index.as_deref_mut(), &fresh_metadata) {
+            index.insert(relative_path, metadata, &entry.hash);
         }
+        manifest.insert(relative_path.to_path_buf(), entry);
     }
 
-    for (path, hash) in &manifest {
-        let object_path = objects_dir.join(hash);
-        if !object_path.exists() {
-            let content = fs::read(root_path.join(path))?;
-            fs::write(object_path, content)?;
-        }
+    // Walks with other options (e.g. `diff -I`) see fewer files, so only forget
+    // files that are gone from disk.
+    if let Some(index) = index {
+        index.retain(|path| manifest.contains_key(path) || root_path.join(path).symlink_metadata().is_ok());
     }
 
-    fs::write(objects_dir.join(&current_manifest_hash), manifest_content)?;
-    
-    history.add_snapshot(message.to_string(), current_manifest_hash);
-    history.save()?;
+    for (path, error) in &results.skipped {
+        warn!("Skipped {}: {}", path, error);
+    }
 
-    let latest = history.get_latest()?;
-    Ok(SaveStatus::Saved {
-        id: latest.id,
-        message: latest.message.clone(),
-    })
+    Ok(manifest)
 }
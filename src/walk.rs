@@ -0,0 +1,130 @@
+//! The directory walker shared by every command that reads the working tree.
+//!
+//! [`WalkOptions`] collects everything that decides which files are visited, so
+//! `cat`, `module`, `save`, `diff` and `revert` all see the same set of files for
+//! the same settings. Files written by devcat itself (see [`utils::FILE_SIGNATURE`])
+//! are always skipped.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::filter::Patterns;
+use crate::utils;
+use ignore::{WalkBuilder, WalkState};
+use log::debug;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// Which files a walk visits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkOptions {
+    pub patterns: Patterns,
+    /// Visit hidden files and directories.
+    pub hidden: bool,
+    /// Honour `.gitignore`, `.ignore` and global git excludes.
+    pub gitignore: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions { patterns: Patterns::default(), hidden: false, gitignore: true }
+    }
+}
+
+impl WalkOptions {
+    /// The options `.devcatrc` asks for, with no command-line overrides.
+    pub fn from_config(config: &Config) -> Self {
+        WalkOptions {
+            patterns: Patterns::with_config(&[], &[], config),
+            hidden: config.hidden,
+            gitignore: config.gitignore,
+        }
+    }
+}
+
+/// Files found by [`walk`] together with the paths that could not be visited.
+pub struct WalkResults<T> {
+    /// Visited files, sorted by path.
+    pub files: Vec<(PathBuf, T)>,
+    pub skipped: Vec<(String, Error)>,
+}
+
+enum Outcome<T> {
+    File(PathBuf, T),
+    Skipped(String, Error),
+}
+
+/// Runs `visit` on every non-directory entry under `root` selected by `options`,
+/// using `jobs` threads (0 picks automatically).
+///
+/// `visit` gets the entry's `symlink_metadata`, so symlinks are passed through as-is;
+/// callers that only want regular files should check `path.is_file()`. It returns
+/// `Ok(None)` to leave a file out silently; errors are collected as skipped items
+/// instead of aborting the walk. Results are sorted by path so output does not
+/// depend on thread scheduling.
+pub fn walk<T, F>(root: &Path, options: &WalkOptions, jobs: usize, visit: F) -> Result<WalkResults<T>>
+where
+    T: Send,
+    F: Fn(&Path, &Metadata) -> Result<Option<T>> + Sync,
+{
+    let filter = options.patterns.matcher(root)?;
+    let walker = WalkBuilder::new(root)
+        .follow_links(false)
+        .hidden(!options.hidden)
+        .git_ignore(options.gitignore)
+        .git_global(options.gitignore)
+        .git_exclude(options.gitignore)
+        .ignore(options.gitignore)
+        .threads(jobs)
+        .filter_entry(move |entry| filter.allows_entry(entry))
+        .build_parallel();
+
+    let visit_entry = |path: &Path| -> Result<Option<Outcome<T>>> {
+        let metadata = path.symlink_metadata()?;
+        if metadata.is_file() && utils::check_file_signature(path)? {
+            debug!("Skipping devcat output file: {}", path.display());
+            return Ok(None);
+        }
+        Ok(visit(path, &metadata)?.map(|value| Outcome::File(path.to_path_buf(), value)))
+    };
+
+    let (tx, rx) = mpsc::channel();
+    walker.run(|| {
+        let tx = tx.clone();
+        let visit_entry = &visit_entry;
+        Box::new(move |result| {
+            let outcome = match result {
+                Ok(entry) if entry.file_type().is_some_and(|t| !t.is_dir()) => {
+                    let path = entry.path();
+                    match visit_entry(path) {
+                        Ok(Some(outcome)) => outcome,
+                        Ok(None) => return WalkState::Continue,
+                        Err(e) => Outcome::Skipped(path.to_string_lossy().to_string(), e),
+                    }
+                }
+                Ok(_) => return WalkState::Continue,
+                Err(e) => {
+                    let path_str = match &e {
+                        ignore::Error::WithPath { path, .. } => path.to_string_lossy().to_string(),
+                        _ => "[Unknown Path]".to_string(),
+                    };
+                    Outcome::Skipped(path_str, e.into())
+                }
+            };
+            let _ = tx.send(outcome);
+            WalkState::Continue
+        })
+    });
+    drop(tx);
+
+    let mut results = WalkResults { files: Vec::new(), skipped: Vec::new() };
+    for outcome in rx {
+        match outcome {
+            Outcome::File(path, value) => results.files.push((path, value)),
+            Outcome::Skipped(path, error) => results.skipped.push((path, error)),
+        }
+    }
+    results.files.sort_by(|a, b| a.0.cmp(&b.0));
+    results.skipped.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(results)
+}
@@ -1,4 +1,4 @@
-use crate::{config, utils, Result};
+use crate::{config, metadata, repository::Repository, utils, walk::WalkOptions, CacheArgs, JobsArgs, Result};
 use clap::Parser;
 use log::{debug, info};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
@@ -8,10 +8,19 @@ use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(about = "Automatically create snapshots when files change.")]
-pub struct WatchArgs {}
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub cache_args: CacheArgs,
+    #[command(flatten)]
+    pub jobs_args: JobsArgs,
+}
 
-pub fn run(_args: WatchArgs) -> Result<()> {
-    let root_path = Path::new(".");
+pub fn run(args: WatchArgs) -> Result<()> {
+    let mut repo = Repository::discover(Path::new("."))?;
+    repo.set_use_index(!args.cache_args.no_cache);
+    repo.set_jobs(args.jobs_args.jobs);
+    let root_path = repo.root();
+    let history_dir = repo.history_dir();
     let config = config::load_config(root_path)?;
 
     let (tx, rx) = channel();
@@ -26,18 +35,17 @@ pub fn run(_args: WatchArgs) -> Result<()> {
                     EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
                 );
 
-                if should_trigger {
-                    if let Some(path) = event.paths.first() {
-                        if !path.starts_with("./.devcat") {
-                            // Send the specific event kind for better logging.
-                            let _ = tx.send(event.kind);
-                        }
-                    }
+                if should_trigger
+                    && let Some(path) = event.paths.first()
+                    && !path.starts_with(&history_dir)
+                {
+                    // Send the specific event kind for better logging.
+                    let _ = tx.send(event.kind);
                 }
             }
         })?;
 
-    watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+    watcher.watch(root_path, RecursiveMode::Recursive)?;
 
     println!("👀 Watching for file changes. Press Ctrl+C to exit.");
 
@@ -50,24 +58,24 @@ pub fn run(_args: WatchArgs) -> Result<()> {
             last_event_time = Some(Instant::now());
         }
 
-        if let Some(last_event) = last_event_time {
-            if last_event.elapsed() >= debounce_duration {
-                let now = chrono::Local::now();
-                let message = format!("Auto-snapshot @ {}", now.format("%Y-%m-%d %H:%M:%S"));
-                
-                match utils::perform_save(root_path, &message, &config.exclude) {
-                    Ok(utils::SaveStatus::Saved { id, message }) => {
-                        println!("\n-- Quiet period ended, snapshot {} created: \"{}\" --", id, message);
-                    }
-                    Ok(utils::SaveStatus::NoChanges) => {
-                        // Silently do nothing if the content hasn't actually changed.
-                    }
-                    Err(e) => {
-                        eprintln!("\nFailed to create auto-snapshot: {}", e);
-                    }
+        if let Some(last_event) = last_event_time
+            && last_event.elapsed() >= debounce_duration
+        {
+            let now = chrono::Local::now();
+            let message = format!("Auto-snapshot @ {}", now.format("%Y-%m-%d %H:%M:%S"));
+            
+            match repo.save(&message, &WalkOptions::from_config(&config), &metadata::Metadata::new()) {
+                Ok(utils::SaveStatus::Saved { id, message }) => {
+                    println!("\n-- Quiet period ended, snapshot {} created: \"{}\" --", id, message);
+                }
+                Ok(utils::SaveStatus::NoChanges) => {
+                    // Silently do nothing if the content hasn't actually changed.
+                }
+                Err(e) => {
+                    eprintln!("\nFailed to create auto-snapshot: {}", e);
                 }
-                last_event_time = None;
             }
+            last_event_time = None;
         }
         
         std::thread::sleep(Duration::from_millis(500));